@@ -50,7 +50,7 @@ impl GitClone {
         GitClone { profile }
     }
 
-    fn git_clone(&self, algo: &str, dir_opt: Option<&str>) {
+    pub(crate) fn git_clone(&self, algo: &str, dir_opt: Option<&str>) {
         let url = format!("{}/git/{}.git", self.profile.git_server(), algo);
         println!("Cloning {}", &url);
 
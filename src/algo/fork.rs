@@ -0,0 +1,162 @@
+use super::GitClone;
+use crate::api::ApiClient;
+use crate::config::Profile;
+use crate::CmdRunner;
+use docopt::Docopt;
+use rustc_serialize::json::Json;
+use std::collections::BTreeMap;
+use std::vec::IntoIter;
+
+static USAGE: &str = r##"Usage:
+  mia fork [options] <algorithm> [<name>]
+
+  Forks an algorithm into the account of the current profile.
+
+  <algorithm> syntax: USERNAME/ALGONAME
+  <name> is the name of the forked algorithm (defaults to ALGONAME)
+
+  Options:
+    --clone                         Clone the forked algorithm after forking
+    --directory <dir>               Directory to clone the fork into (implies --clone)
+
+  Examples:
+    mia fork anowell/bcrypt                              Fork an algorithm
+    mia fork anowell/bcrypt bcrypt-patched --clone       Fork with a new name and clone it
+    mia fork anowell/bcrypt --directory src/bcrypt       Fork and clone into a specific directory
+"##;
+
+#[derive(RustcDecodable, Debug)]
+struct Args {
+    arg_algorithm: String,
+    arg_name: Option<String>,
+    flag_clone: bool,
+    flag_directory: Option<String>,
+}
+
+pub struct Fork {
+    client: ApiClient,
+    profile: Profile,
+}
+impl CmdRunner for Fork {
+    fn get_usage() -> &'static str {
+        USAGE
+    }
+
+    fn cmd_main(&self, argv: IntoIter<String>) {
+        let args: Args = Docopt::new(USAGE)
+            .and_then(|d| d.argv(argv).decode())
+            .unwrap_or_else(|e| e.exit());
+
+        let algo = args.arg_algorithm.trim_start_matches("algo://");
+        let parts: Vec<&str> = algo.trim_matches('/').split('/').collect();
+        if parts.len() != 2 {
+            quit_msg!("Invalid algorithm '{}'\n\n{}", args.arg_algorithm, USAGE);
+        }
+
+        let fork = self.fork(parts[0], parts[1], args.arg_name.as_deref());
+        println!("Forked {}/{} to {}", parts[0], parts[1], fork);
+
+        if args.flag_clone || args.flag_directory.is_some() {
+            GitClone::new(self.profile.clone()).git_clone(&fork, args.flag_directory.as_deref());
+        }
+    }
+}
+
+impl Fork {
+    pub fn new(profile: Profile) -> Self {
+        Fork {
+            client: profile.api_client(),
+            profile,
+        }
+    }
+
+    // Forks the algorithm, returning the USERNAME/ALGONAME of the fork
+    fn fork(&self, user: &str, algo: &str, name: Option<&str>) -> String {
+        let mut body = BTreeMap::new();
+        if let Some(name) = name {
+            body.insert("name".to_string(), Json::String(name.to_owned()));
+        }
+
+        let forked = self
+            .client
+            .post(
+                &format!("/v1/algorithms/{}/{}/fork", user, algo),
+                &Json::Object(body),
+            )
+            .unwrap_or_else(|err| quit_msg!("Error forking {}/{}: {}", user, algo, err));
+
+        forked_algo(&forked).unwrap_or_else(|err| {
+            quit_msg!("Unexpected response forking {}/{}: {}", user, algo, err)
+        })
+    }
+}
+
+// The USERNAME/ALGONAME of a forked algorithm, from its self link
+//   e.g. "https://api.algorithmia.com/v1/algorithms/jdoe/bcrypt", since there's no owner field
+fn forked_algo(forked: &Json) -> Result<String, String> {
+    let link = match forked.find("self_link") {
+        Some(Json::String(link)) => link,
+        Some(other) => return Err(format!("'self_link' is not a string: {}", other)),
+        None => return Err(format!("missing 'self_link' in {}", forked)),
+    };
+    let not_algo = || format!("'self_link' is not an algorithm link: {}", link);
+    let prefix = "/v1/algorithms/";
+    let start = link.find(prefix).ok_or_else(not_algo)? + prefix.len();
+    let parts: Vec<&str> = link[start..].trim_matches('/').split('/').collect();
+    match parts.as_slice() {
+        [owner, name] if !owner.is_empty() && !name.is_empty() => Ok(format!("{}/{}", owner, name)),
+        _ => Err(not_algo()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_forked_algo() {
+        let response = r#"{
+            "id": "3f2b9a1c-8d4e-4b7a-9c6d-2e1f0a9b8c7d",
+            "name": "bcrypt_patched",
+            "details": {"label": "bcrypt", "summary": "Hashes passwords"},
+            "settings": {"algorithm_callability": "private", "source_visibility": "closed",
+                         "language": "rust", "environment": "cpu", "license": "apl",
+                         "royalty_microcredits": 0, "network_access": "isolated",
+                         "pipeline_enabled": false},
+            "source": {"scm": {"id": "internal", "provider": "internal", "default": true,
+                               "enabled": true}},
+            "resource_type": "algorithm",
+            "self_link": "https://api.algorithmia.com/v1/algorithms/jdoe/bcrypt_patched"
+        }"#;
+        let forked = Json::from_str(response).unwrap();
+        assert_eq!(forked_algo(&forked).as_deref(), Ok("jdoe/bcrypt_patched"));
+
+        let forked_algo = |response| forked_algo(&Json::from_str(response).unwrap());
+        assert_eq!(
+            forked_algo(
+                r#"{"self_link": "https://api.algorithmia.com/v1/algorithms/jdoe/bcrypt/"}"#
+            ),
+            Ok("jdoe/bcrypt".to_string())
+        );
+
+        // Missing or partial owners are errors naming the field rather than a partial USERNAME/ALGONAME
+        assert_eq!(
+            forked_algo(r#"{"name": "bcrypt"}"#),
+            Err(r#"missing 'self_link' in {"name":"bcrypt"}"#.to_string())
+        );
+        assert_eq!(
+            forked_algo(r#"{"self_link": null}"#),
+            Err("'self_link' is not a string: null".to_string())
+        );
+        let err = forked_algo(
+            r#"{"self_link": "https://api.algorithmia.com/v1/algorithms/jdoe/bcrypt/versions"}"#,
+        )
+        .unwrap_err();
+        assert!(err.starts_with("'self_link' is not an algorithm link: "));
+        assert!(forked_algo(
+            r#"{"self_link": "https://api.algorithmia.com/v1/algorithms//bcrypt"}"#
+        )
+        .is_err());
+        assert!(forked_algo(r#"{"self_link": "https://api.algorithmia.com/bcrypt"}"#).is_err());
+    }
+}
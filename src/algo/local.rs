@@ -0,0 +1,363 @@
+use super::InputData;
+use crate::DynError;
+use rustc_serialize::base64::{ToBase64, STANDARD};
+use rustc_serialize::json::Json;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Algorithm config file generated in every algorithm repo
+static ALGO_CONFIG: &str = "algorithmia.conf";
+
+// Entry point for algorithms that ship their own langpack scripts
+static LANGPACK_BUILD: &str = "bin/build";
+static LANGPACK_PIPE: &str = "bin/pipe";
+
+// Runs `apply` from a python algorithm per the pipe protocol described on `LocalProcess`
+static PYTHON_PIPE: &str = r#"
+import base64, importlib, json, os, sys, traceback
+sys.path.insert(0, 'src')
+algo = importlib.import_module(os.environ['ALGO_MODULE'])
+out = open(os.environ['ALGOOUT'], 'w')
+for line in iter(sys.stdin.readline, ''):
+    req = json.loads(line)
+    data = req['data']
+    if req['content_type'] == 'binary':
+        data = bytearray(base64.b64decode(data))
+    try:
+        result = algo.apply(data)
+        if isinstance(result, bytearray) or (bytes is not str and isinstance(result, bytes)):
+            res = {'result': base64.b64encode(result).decode('ascii'), 'metadata': {'content_type': 'binary'}}
+        elif isinstance(result, type(u'')) or isinstance(result, str):
+            res = {'result': result, 'metadata': {'content_type': 'text'}}
+        else:
+            res = {'result': result, 'metadata': {'content_type': 'json'}}
+    except Exception as e:
+        res = {'error': {'message': str(e), 'error_type': 'AlgorithmError', 'stacktrace': traceback.format_exc()}}
+    sys.stdout.flush()
+    out.write(json.dumps(res) + '\n')
+    out.flush()
+"#;
+
+#[derive(Debug, Clone)]
+enum Runner {
+    Langpack,
+    Python(&'static str),
+}
+
+/// An algorithm checked out locally (e.g. via `mia clone`)
+#[derive(Debug, Clone)]
+pub(crate) struct LocalAlgo {
+    dir: PathBuf,
+    algoname: String,
+    language: String,
+    runner: Runner,
+}
+
+impl LocalAlgo {
+    pub(crate) fn from_dir(dir: &Path) -> Result<LocalAlgo, DynError> {
+        let config_path = dir.join(ALGO_CONFIG);
+        let mut config = String::new();
+        File::open(&config_path)
+            .and_then(|mut f| f.read_to_string(&mut config))
+            .map_err(|err| {
+                format!(
+                    "Unable to read {} (is this an algorithm repo?): {}",
+                    config_path.display(),
+                    err
+                )
+            })?;
+        let config = Json::from_str(&config)
+            .map_err(|err| format!("Unable to parse {}: {}", config_path.display(), err))?;
+
+        let field = |name: &str| -> Result<String, DynError> {
+            config
+                .find(name)
+                .and_then(Json::as_string)
+                .map(String::from)
+                .ok_or_else(|| format!("{} is missing '{}'", config_path.display(), name).into())
+        };
+        let language = field("language")?;
+        let algoname = field("algoname")?;
+
+        // Prefer the algorithm's own langpack scripts if present
+        let runner = if dir.join(LANGPACK_PIPE).is_file() {
+            Runner::Langpack
+        } else if language.starts_with("python3") {
+            Runner::Python("python3")
+        } else if language.starts_with("python2") {
+            Runner::Python("python2")
+        } else if language.starts_with("python") {
+            Runner::Python("python")
+        } else {
+            return Err(format!(
+                "Running '{}' algorithms locally requires {} and {} scripts",
+                language, LANGPACK_BUILD, LANGPACK_PIPE
+            )
+            .into());
+        };
+
+        Ok(LocalAlgo {
+            dir: dir.to_owned(),
+            algoname,
+            language,
+            runner,
+        })
+    }
+
+    pub(crate) fn language(&self) -> &str {
+        &self.language
+    }
+
+    pub(crate) fn algoname(&self) -> &str {
+        &self.algoname
+    }
+
+    /// Builds the algorithm, optionally echoing build output to stderr
+    pub(crate) fn build(&self, echo_stdout: bool) -> Result<(), DynError> {
+        let mut cmd = match self.runner {
+            Runner::Langpack if self.dir.join(LANGPACK_BUILD).is_file() => {
+                Command::new(self.dir.join(LANGPACK_BUILD))
+            }
+            Runner::Python(python) if self.dir.join("requirements.txt").is_file() => {
+                let mut cmd = Command::new(python);
+                cmd.args(["-m", "pip", "install", "-r", "requirements.txt"]);
+                cmd
+            }
+            // Nothing to build
+            _ => return Ok(()),
+        };
+
+        // Build output is informational, so keep it off of stdout
+        let status = cmd
+            .current_dir(&self.dir)
+            .stdout(Stdio::piped())
+            .spawn()
+            .and_then(|mut child| {
+                if let Some(ref mut out) = child.stdout {
+                    match echo_stdout {
+                        true => io::copy(out, &mut io::stderr())?,
+                        false => io::copy(out, &mut io::sink())?,
+                    };
+                }
+                child.wait()
+            })
+            .map_err(|err| format!("Failed to run build: {}", err))?;
+
+        match status.success() {
+            true => Ok(()),
+            false => Err(format!("Build failed ({})", status).into()),
+        }
+    }
+
+    /// Starts the algorithm process, optionally echoing its stdout to stderr in real-time
+    pub(crate) fn start(&self, echo_stdout: bool) -> Result<LocalProcess, DynError> {
+        LocalProcess::start(self, echo_stdout)
+    }
+}
+
+/// A running algorithm process that handles one request at a time
+///
+/// Each request is written to the process's stdin as one line of JSON:
+///   `{"content_type": "json"|"text"|"binary", "data": ...}` (binary data is base64 encoded)
+///
+/// Each response is written as one line of JSON to the FIFO at `$ALGOOUT`:
+///   `{"result": ..., "metadata": {"content_type": ...}}` or `{"error": {"message": ...}}`
+///
+/// Using a FIFO for responses leaves the algorithm free to print to stdout and stderr.
+pub(crate) struct LocalProcess {
+    child: Child,
+    stdin: ChildStdin,
+    responses: Receiver<String>,
+    fifo_dir: PathBuf,
+}
+
+impl LocalProcess {
+    #[cfg(unix)]
+    fn start(algo: &LocalAlgo, echo_stdout: bool) -> Result<LocalProcess, DynError> {
+        let fifo_dir = unique_temp_dir()?;
+        let fifo_path = fifo_dir.join("algoout");
+        let mkfifo = Command::new("mkfifo")
+            .arg(&fifo_path)
+            .status()
+            .map_err(|err| format!("Failed to run mkfifo: {}", err))?;
+        if !mkfifo.success() {
+            return Err(format!("Failed to create {}", fifo_path.display()).into());
+        }
+
+        let mut cmd = match algo.runner {
+            Runner::Langpack => Command::new(algo.dir.join(LANGPACK_PIPE)),
+            Runner::Python(python) => {
+                let mut cmd = Command::new(python);
+                cmd.arg("-u").arg("-c").arg(PYTHON_PIPE);
+                cmd
+            }
+        };
+        let mut child = cmd
+            .current_dir(&algo.dir)
+            .env("ALGOOUT", &fifo_path)
+            .env("ALGO_MODULE", &algo.algoname)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|err| format!("Failed to start algorithm: {}", err))?;
+
+        // Algorithm stdout is relayed to stderr so that stdout is reserved for the result
+        let mut stdout = child.stdout.take().unwrap();
+        thread::spawn(move || match echo_stdout {
+            true => io::copy(&mut stdout, &mut io::stderr()),
+            false => io::copy(&mut stdout, &mut io::sink()),
+        });
+
+        // Opening the FIFO blocks until the algorithm opens it for writing.
+        // It's reopened after EOF since some algorithms reopen $ALGOOUT for each response.
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            while let Ok(fifo) = File::open(&fifo_path) {
+                for line in BufReader::new(fifo).lines() {
+                    match line {
+                        Ok(l) => {
+                            if tx.send(l).is_err() {
+                                return;
+                            }
+                        }
+                        Err(_) => return,
+                    }
+                }
+            }
+        });
+
+        let stdin = child.stdin.take().unwrap();
+        Ok(LocalProcess {
+            child,
+            stdin,
+            responses: rx,
+            fifo_dir,
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn start(_algo: &LocalAlgo, _echo_stdout: bool) -> Result<LocalProcess, DynError> {
+        Err("Running algorithms locally is only supported on Unix-like systems".into())
+    }
+
+    /// Sends the input to the algorithm and waits for the JSON response body
+    ///
+    /// The response metadata is amended with the call duration to match the Algorithmia API
+    pub(crate) fn call(&mut self, input: &InputData) -> Result<String, DynError> {
+        let request = pipe_request(input)?;
+        self.send(&request)
+    }
+
+    /// Sends a request built by `pipe_request`, where any error means the process failed
+    pub(crate) fn send(&mut self, request: &Json) -> Result<String, DynError> {
+        let start = Instant::now();
+        writeln!(self.stdin, "{}", request)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|err| format!("Failed to send input to algorithm: {}", err))?;
+
+        loop {
+            match self.responses.recv_timeout(Duration::from_millis(100)) {
+                Ok(line) => return Ok(with_duration(&line, start.elapsed())),
+                Err(RecvTimeoutError::Timeout) => {
+                    if let Some(status) = self.child.try_wait()? {
+                        return Err(format!("Algorithm exited unexpectedly ({})", status).into());
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err("Algorithm closed its output".into());
+                }
+            }
+        }
+    }
+}
+
+impl Drop for LocalProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = fs::remove_dir_all(&self.fifo_dir);
+    }
+}
+
+fn unique_temp_dir() -> Result<PathBuf, DynError> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "mia-{}-{}",
+        process::id(),
+        COUNTER.fetch_add(1, Ordering::SeqCst)
+    ));
+    fs::create_dir_all(&dir)
+        .map_err(|err| format!("Failed to create {}: {}", dir.display(), err))?;
+    Ok(dir)
+}
+
+/// The line sent to the algorithm for an input, which fails if JSON input is invalid
+pub(crate) fn pipe_request(input: &InputData) -> Result<Json, DynError> {
+    let (content_type, data) = match *input {
+        InputData::Text(ref text) => ("text", Json::String(text.clone())),
+        InputData::Json(ref json) => (
+            "json",
+            Json::from_str(json).map_err(|err| format!("Invalid JSON input: {}", err))?,
+        ),
+        InputData::Binary(ref bytes) => ("binary", Json::String(bytes.to_base64(STANDARD))),
+    };
+
+    let mut request = BTreeMap::new();
+    request.insert(
+        "content_type".to_string(),
+        Json::String(content_type.into()),
+    );
+    request.insert("data".to_string(), data);
+    Ok(Json::Object(request))
+}
+
+fn with_duration(response: &str, duration: Duration) -> String {
+    match Json::from_str(response) {
+        Ok(Json::Object(mut obj)) => {
+            if let Some(&mut Json::Object(ref mut metadata)) = obj.get_mut("metadata") {
+                let secs = duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1e9;
+                metadata
+                    .entry("duration".to_string())
+                    .or_insert(Json::F64(secs));
+            }
+            Json::Object(obj).to_string()
+        }
+        _ => response.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pipe_request() {
+        let text = pipe_request(&InputData::Text("hi".into())).unwrap();
+        assert_eq!(text.to_string(), r#"{"content_type":"text","data":"hi"}"#);
+
+        let json = pipe_request(&InputData::Json("[1, 2]".into())).unwrap();
+        assert_eq!(json.to_string(), r#"{"content_type":"json","data":[1,2]}"#);
+
+        let binary = pipe_request(&InputData::Binary(vec![0, 1, 2])).unwrap();
+        assert_eq!(
+            binary.to_string(),
+            r#"{"content_type":"binary","data":"AAEC"}"#
+        );
+    }
+
+    #[test]
+    fn test_with_duration() {
+        let response = r#"{"metadata":{"content_type":"json"},"result":1}"#;
+        assert_eq!(
+            with_duration(response, Duration::from_millis(1500)),
+            r#"{"metadata":{"content_type":"json","duration":1.5},"result":1}"#
+        );
+    }
+}
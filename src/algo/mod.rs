@@ -1,8 +1,17 @@
 pub use self::clone::GitClone;
+pub use self::fork::Fork;
 pub use self::run::Run;
+pub use self::runlocal::RunLocal;
+pub use self::serve::Serve;
+pub use self::view::View;
 
 mod clone;
+mod fork;
+mod local;
 mod run;
+mod runlocal;
+mod serve;
+mod view;
 
 use crate::{color_choice, BRIGHT_RED, GRAY};
 use algorithmia::algo::{AlgoResponse, Response};
@@ -13,8 +22,8 @@ use std::path::Path;
 use std::vec::IntoIter;
 use termcolor::{Color, ColorSpec, StandardStream, WriteColor};
 
-#[derive(Debug)]
-enum InputData {
+#[derive(Debug, PartialEq)]
+pub(crate) enum InputData {
     Text(String),
     Json(String),
     Binary(Vec<u8>),
@@ -123,29 +132,43 @@ struct ResponseConfig {
     flag_output: Option<String>,
 }
 
-fn display_response(mut response: Response, config: ResponseConfig) {
-    // Open up an output device for the result/response
-    let mut output = OutputDevice::new(&config.flag_output);
-    let mut t_err = StandardStream::stderr(color_choice());
-
+// Displays an algorithm response, returning false if the call failed (after printing its error)
+fn display_response(mut response: Response, config: ResponseConfig) -> bool {
     // Read JSON response - scoped so that we can re-borrow response
     let mut json_response = String::new();
     {
         if let Err(err) = response.read_to_string(&mut json_response) {
-            quit_err!("Error reading response: {}", err)
+            eprintln_red!("Error reading response: {}", err);
+            crate::print_cause_chain(&err);
+            return false;
         };
     }
 
+    let preamble = format!(
+        "{:?} {}\n{:?}",
+        response.version(),
+        response.status(),
+        response.headers()
+    );
+    display_response_body(&json_response, Some(preamble), config)
+}
+
+// Displays a JSON response body, optionally preceded by the HTTP preamble for --response
+fn display_response_body(
+    json_response: &str,
+    preamble: Option<String>,
+    config: ResponseConfig,
+) -> bool {
+    // Open up an output device for the result/response
+    let mut output = OutputDevice::new(&config.flag_output);
+    let mut t_err = StandardStream::stderr(color_choice());
+
     // Handle --response and --response-body (ignoring other flags)
     if config.flag_response || config.flag_response_body {
         if config.flag_response {
-            let preamble = format!(
-                "{:?} {}\n{:?}",
-                response.version(),
-                response.status(),
-                response.headers()
-            );
-            output.writeln(preamble.as_bytes());
+            if let Some(preamble) = preamble {
+                output.writeln(preamble.as_bytes());
+            }
         };
         output.writeln(json_response.as_bytes());
     } else {
@@ -188,7 +211,7 @@ fn display_response(mut response: Response, config: ResponseConfig) {
                     None => match response.result.to_json() {
                         Some(j) => output.writeln(j.as_bytes()),
                         None => output.write(response.result.as_bytes().unwrap()),
-                    }
+                    },
                 };
             }
             Err(ref error) if error.api_error().is_some() => {
@@ -201,16 +224,19 @@ fn display_response(mut response: Response, config: ResponseConfig) {
                 if let Some(ref trace) = err.stacktrace {
                     eprintln!("{}", trace)
                 }
-                ::std::process::exit(1);
+                return false;
             }
             Err(err) => {
-                quit_err!(
+                eprintln_red!(
                     "Failed to parse algorithm response (debug with --response-body)\n{}",
                     err
-                )
+                );
+                crate::print_cause_chain(&err);
+                return false;
             }
         };
     }
+    true
 }
 
 // separates input-defining args from other args
@@ -250,12 +276,5 @@ fn split_args(argv: IntoIter<String>, usage: &'static str) -> (Vec<InputData>, V
         };
     }
 
-    // Validating args and options
-    if input_args.len() < 1 {
-        quit_msg!("Must specify an input data option\n\n{}", usage);
-    } else if input_args.len() > 1 {
-        quit_msg!("Multiple input data sources is currently not supported");
-    }
-
     (input_args, other_args)
 }
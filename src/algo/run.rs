@@ -1,10 +1,19 @@
-use super::{display_response, split_args, InputData, ResponseConfig};
+use super::{display_response, get_src, split_args, InputData, OutputDevice, ResponseConfig};
 use crate::config::Profile;
 use crate::CmdRunner;
 use algorithmia::algo::{AlgoOptions, Response};
+use algorithmia::error::Error;
 use algorithmia::Algorithmia;
+use chan::Receiver;
 use docopt::Docopt;
+use rustc_serialize::json::Json;
+use std::collections::BTreeMap;
+use std::error::Error as StdError;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex};
 use std::vec::IntoIter;
+use std::{cmp, fmt, thread};
 
 static USAGE: &'static str = r##"Usage:
   mia run [options] <algorithm>
@@ -16,6 +25,11 @@ static USAGE: &'static str = r##"Usage:
     There are option variants for specifying the type and source of input data.
     If <file> is '-', then input data will be read from STDIN.
 
+    Input data options may be repeated, in which case each input is a separate algorithm
+    call. Calls are made concurrently (see --parallel), and results are printed in the
+    order the inputs were specified. A failed input doesn't stop the others, but the
+    failures are listed at the end (and exit non-zero).
+
     Auto-Detect Data:
       -d, --data <data>             If the data parses as JSON, assume JSON, else if the data
                                       is valid UTF-8, assume text, else assume binary
@@ -33,6 +47,11 @@ static USAGE: &'static str = r##"Usage:
       -b, --binary <data>           Algorithm input data as binary (application/octet-stream)
       -B, --binary-file <file>      Same as --data, but the input data is read from a file
 
+    JSON Lines Data:
+      --jsonl <file>                Each non-blank line of the file is a separate JSON input.
+                                      Output is written as JSON Lines with one record per input:
+                                      {"input_index", "result", "metadata", "error"}
+
 
   Output Options:
     By default, only the algorithm result is printed to STDOUT while additional notices may be
@@ -43,16 +62,27 @@ static USAGE: &'static str = r##"Usage:
     --response-body                 Print HTTP response body (replaces result)
     --response                      Print full HTTP response including headers (replaces result)
     -s, --silence                   Suppress any output not explicitly requested (except result)
-    -o, --output <file>             Print result to a file
+    -o, --output <file>             Print result to a file. With multiple inputs, each result is
+                                      written to its own file: '{}' in <file> is replaced with
+                                      the input index, otherwise the index is inserted before
+                                      the file extension (e.g. out.0.json, out.1.json)
 
   Other Options:
     --timeout <seconds>             Sets algorithm timeout
+    --parallel <n>                  Max number of concurrent calls with multiple inputs [Default: 4]
+    --continue-on-error             With --jsonl, record errors and keep going instead of stopping
+                                      at the first failed input (exits non-zero if any failed)
 
   Examples:
     mia run kenny/factor/0.1.0 -d '79'                   Run algorithm with specified data input
     mia run anowell/Dijkstra -D routes.json              Run algorithm with file input
     mia run anowell/Dijkstra -D - < routes.json          Same as above but using STDIN
     mia run opencv/SmartThumbnail -D in.png -o out.png   Run algorithm saving output to a file
+    mia run kenny/factor -d 17 -d 42 -d 79               Run algorithm once for each input
+    mia run opencv/SmartThumbnail -D a.png -D b.png -o 'thumb-{}.png'
+                                                         Run algorithm saving each output to a file
+    mia run nlp/SentimentAnalysis --jsonl in.jsonl --parallel 8 --continue-on-error > out.jsonl
+                                                         Run algorithm for every line of a JSONL file
 "##;
 
 #[derive(RustcDecodable, Debug)]
@@ -66,6 +96,9 @@ struct Args {
     flag_no_debug: bool,
     flag_output: Option<String>,
     flag_timeout: Option<u32>,
+    flag_parallel: u32,
+    flag_jsonl: Option<String>,
+    flag_continue_on_error: bool,
 }
 
 pub struct Run {
@@ -85,19 +118,39 @@ impl CmdRunner for Run {
             .and_then(|d| d.argv(other_args).decode())
             .unwrap_or_else(|e| e.exit());
 
+        // Without any calls in flight, queuing the first input would block forever
+        if args.flag_parallel < 1 {
+            quit_msg!("--parallel must be at least 1");
+        }
+
         // --debug can override --silence, but the lack of --debug respects --silence
         let debug = args.flag_debug || !(args.flag_no_debug || args.flag_silence);
 
-        let mut opts = AlgoOptions::default();
-        if debug {
-            opts.stdout(true);
-        }
-        if let Some(timeout) = args.flag_timeout {
-            opts.timeout(timeout);
+        match (args.flag_jsonl.as_ref(), input_args.len()) {
+            (Some(src), 0) => return self.run_jsonl(&args, src, debug),
+            (Some(_), _) => quit_msg!("--jsonl cannot be combined with other input data options"),
+            (None, 0) => quit_msg!("Must specify an input data option\n\n{}", USAGE),
+            (None, 1) => (),
+            (None, count) => {
+                let failed = self.run_batch(&args, input_args, debug);
+                if !failed.is_empty() {
+                    let indexes: Vec<String> = failed.iter().map(|i| i.to_string()).collect();
+                    quit_msg!(
+                        "{} of {} inputs failed: {}",
+                        failed.len(),
+                        count,
+                        indexes.join(", ")
+                    );
+                }
+                return;
+            }
         }
 
         // Run the algorithm
-        let response = self.run_algorithm(&*args.arg_algorithm, input_args.remove(0), opts);
+        let opts = algo_options(debug, args.flag_timeout);
+        let response = self
+            .run_algorithm(&*args.arg_algorithm, input_args.remove(0), opts)
+            .unwrap_or_else(|err| quit_err!("Error calling algorithm: {}", err));
 
         let config = ResponseConfig {
             flag_response_body: args.flag_response_body,
@@ -107,7 +160,9 @@ impl CmdRunner for Run {
             flag_output: args.flag_output,
         };
 
-        display_response(response, config);
+        if !display_response(response, config) {
+            ::std::process::exit(1);
+        }
     }
 }
 
@@ -118,19 +173,370 @@ impl Run {
         }
     }
 
-    fn run_algorithm(&self, algo: &str, input_data: InputData, opts: AlgoOptions) -> Response {
+    fn run_algorithm(
+        &self,
+        algo: &str,
+        input_data: InputData,
+        opts: AlgoOptions,
+    ) -> Result<Response, Error> {
         let mut algorithm = self.client.algo(algo);
         let algorithm = algorithm.set_options(opts);
 
-        let result = match input_data {
+        match input_data {
             InputData::Text(text) => algorithm.pipe_as(text, mime::TEXT_PLAIN),
             InputData::Json(json) => algorithm.pipe_as(json, mime::APPLICATION_JSON),
             InputData::Binary(bytes) => algorithm.pipe_as(bytes, mime::APPLICATION_OCTET_STREAM),
-        };
+        }
+    }
+
+    // Calls the algorithm once per input with bounded concurrency,
+    //   displaying each response in input order as soon as it's available.
+    //   Returns the indexes of the inputs that failed, which don't stop the other calls.
+    fn run_batch(&self, args: &Args, input_args: Vec<InputData>, debug: bool) -> Vec<usize> {
+        let input_count = input_args.len();
+        let concurrency = cmp::min(input_count, args.flag_parallel as usize);
+
+        let (tx, rx) = chan::sync(concurrency);
+
+        // One Producer thread queuing up the indexed inputs
+        thread::spawn(move || {
+            for (index, input_data) in input_args.into_iter().enumerate() {
+                tx.send((index, Ok(input_data)));
+            }
+            drop(tx);
+        });
+
+        let mut failed = Vec::new();
+        let completed = self.call_in_order(args, debug, concurrency, rx, |index, result| {
+            let response = match result {
+                Ok(response) => response,
+                Err(err) => {
+                    eprintln_red!("Error calling algorithm (input {}): {}", index, err);
+                    crate::print_cause_chain(&err);
+                    return failed.push(index);
+                }
+            };
+            let config = ResponseConfig {
+                flag_response_body: args.flag_response_body,
+                flag_response: args.flag_response,
+                flag_silence: args.flag_silence,
+                flag_debug: debug,
+                flag_output: args
+                    .flag_output
+                    .as_ref()
+                    .map(|path| indexed_output_path(path, index)),
+            };
+            if !display_response(response, config) {
+                failed.push(index);
+            }
+        });
+
+        if completed < input_count {
+            quit_msg!("Only {} of {} inputs completed", completed, input_count);
+        }
+        failed
+    }
+
+    // Calls the algorithm once per line of JSON input, writing a JSON Lines record per result
+    fn run_jsonl(&self, args: &Args, src: &str, debug: bool) {
+        let concurrency = args.flag_parallel as usize;
+        let (tx, rx) = chan::sync(concurrency);
 
-        match result {
-            Ok(response) => response,
-            Err(err) => quit_err!("Error calling algorithm: {} {}", 1, err),
+        // One Producer thread reading lines so that large inputs are streamed
+        let src = src.to_owned();
+        thread::spawn(move || {
+            let reader = BufReader::new(get_src(&src));
+            let lines = reader.lines().filter(|line| match *line {
+                Ok(ref l) => !l.trim().is_empty(),
+                Err(_) => true,
+            });
+            for (index, line) in lines.enumerate() {
+                let line = line.unwrap_or_else(|err| quit_err!("Read error: {}", err));
+                let input_data = match Json::from_str(&line) {
+                    Ok(_) => Ok(InputData::Json(line)),
+                    Err(err) => Err(Error::from(format!("Invalid JSON input: {}", err))),
+                };
+                tx.send((index, input_data));
+            }
+            drop(tx);
+        });
+
+        let mut output = OutputDevice::new(&args.flag_output);
+        let mut failures = 0;
+        let completed = self.call_in_order(args, debug, concurrency, rx, |index, result| {
+            let record = match result {
+                Ok(mut response) => JsonlRecord::from_response(index, &mut response),
+                Err(err) => JsonlRecord::from_error(index, &err),
+            };
+            output.writeln(record.to_string().as_bytes());
+
+            if record.error.is_some() {
+                failures += 1;
+                if !args.flag_continue_on_error {
+                    quit_msg!(
+                        "Stopping after error on input {} (use --continue-on-error to keep going)",
+                        index
+                    );
+                }
+            }
+        });
+
+        if !args.flag_silence {
+            eprintln!(
+                "Completed {} input(s) with {} error(s)",
+                completed, failures
+            );
         }
+        if failures > 0 {
+            ::std::process::exit(1);
+        }
+    }
+
+    // Calls the algorithm for each input received on `inputs` with bounded concurrency,
+    //   passing each result to `handle` in input order as soon as it's available.
+    //   Calls only start up to `2 * concurrency` inputs ahead of the next result to handle,
+    //   so a slow call can't leave every later response (and its connection) buffered.
+    //   Returns the number of results handled.
+    fn call_in_order<F>(
+        &self,
+        args: &Args,
+        debug: bool,
+        concurrency: usize,
+        inputs: Receiver<(usize, Result<InputData, Error>)>,
+        mut handle: F,
+    ) -> usize
+    where
+        F: FnMut(usize, Result<Response, Error>),
+    {
+        let (res_tx, res_rx) = chan::sync(concurrency);
+        let max_ahead = 2 * concurrency;
+        let next = Arc::new((Mutex::new(0), Condvar::new()));
+
+        // Spin up threads to concurrently call the algorithm with inputs received on rx channel
+        for _ in 0..concurrency {
+            let thread_rx = inputs.clone();
+            let thread_res_tx = res_tx.clone();
+            let thread_run = Run {
+                client: self.client.clone(),
+            };
+            let algo = args.arg_algorithm.clone();
+            let timeout = args.flag_timeout;
+            let next = next.clone();
+
+            thread::spawn(move || {
+                for (index, input_data) in thread_rx {
+                    // Inputs are received in order, so the next result's call has already started
+                    let (ref lock, ref cvar) = *next;
+                    let mut next_index = lock.lock().unwrap();
+                    while index >= *next_index + max_ahead {
+                        next_index = cvar.wait(next_index).unwrap();
+                    }
+                    drop(next_index);
+
+                    let result = input_data.and_then(|data| {
+                        let opts = algo_options(debug, timeout);
+                        thread_run.run_algorithm(&algo, data, opts)
+                    });
+                    thread_res_tx.send((index, result));
+                }
+            });
+        }
+        drop(res_tx);
+
+        // Responses may complete out of order, so buffer them until it's their turn
+        let mut pending = BTreeMap::new();
+        let mut next_index = 0;
+        for (index, result) in res_rx {
+            pending.insert(index, result);
+            while let Some(result) = pending.remove(&next_index) {
+                handle(next_index, result);
+                next_index += 1;
+            }
+            let (ref lock, ref cvar) = *next;
+            *lock.lock().unwrap() = next_index;
+            cvar.notify_all();
+        }
+        next_index
+    }
+}
+
+// One line of --jsonl output
+struct JsonlRecord {
+    input_index: usize,
+    result: Json,
+    metadata: Json,
+    error: Option<Json>,
+}
+
+impl JsonlRecord {
+    fn from_response(input_index: usize, response: &mut Response) -> JsonlRecord {
+        let mut body = String::new();
+        if let Err(err) = response.read_to_string(&mut body) {
+            return JsonlRecord::from_error(input_index, &err);
+        }
+
+        match Json::from_str(&body) {
+            Ok(Json::Object(mut obj)) => match obj.remove("error") {
+                Some(error) => JsonlRecord {
+                    input_index,
+                    result: Json::Null,
+                    metadata: obj.remove("metadata").unwrap_or(Json::Null),
+                    error: Some(error),
+                },
+                None => JsonlRecord {
+                    input_index,
+                    result: obj.remove("result").unwrap_or(Json::Null),
+                    metadata: obj.remove("metadata").unwrap_or(Json::Null),
+                    error: None,
+                },
+            },
+            _ => JsonlRecord::from_message(
+                input_index,
+                format!("Failed to parse algorithm response: {}", body),
+            ),
+        }
+    }
+
+    fn from_error(input_index: usize, err: &dyn StdError) -> JsonlRecord {
+        // Flatten the cause chain since the top-level error often lacks the root cause
+        let mut message = err.to_string();
+        let mut cause = err.source();
+        while let Some(e) = cause {
+            message = format!("{}: {}", message, e);
+            cause = e.source();
+        }
+        JsonlRecord::from_message(input_index, message)
+    }
+
+    fn from_message(input_index: usize, message: String) -> JsonlRecord {
+        let mut error = BTreeMap::new();
+        error.insert("message".to_string(), Json::String(message));
+        JsonlRecord {
+            input_index,
+            result: Json::Null,
+            metadata: Json::Null,
+            error: Some(Json::Object(error)),
+        }
+    }
+}
+
+impl fmt::Display for JsonlRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            r#"{{"input_index":{},"result":{},"metadata":{},"error":{}}}"#,
+            self.input_index,
+            self.result,
+            self.metadata,
+            self.error.as_ref().unwrap_or(&Json::Null)
+        )
+    }
+}
+
+fn algo_options(debug: bool, timeout: Option<u32>) -> AlgoOptions {
+    let mut opts = AlgoOptions::default();
+    if debug {
+        opts.stdout(true);
+    }
+    if let Some(timeout) = timeout {
+        opts.timeout(timeout);
+    }
+    opts
+}
+
+// Derives a per-input output path by replacing '{}' with the input index,
+//   or if there is no '{}', by inserting the index before the file extension
+fn indexed_output_path(path: &str, index: usize) -> String {
+    if path.contains("{}") {
+        return path.replace("{}", &index.to_string());
+    }
+
+    let p = Path::new(path);
+    match (p.file_stem(), p.extension()) {
+        (Some(stem), Some(ext)) => p
+            .with_file_name(format!(
+                "{}.{}.{}",
+                stem.to_string_lossy(),
+                index,
+                ext.to_string_lossy()
+            ))
+            .to_string_lossy()
+            .into_owned(),
+        _ => format!("{}.{}", path, index),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{env, fs, process};
+
+    #[test]
+    fn test_indexed_output_path() {
+        assert_eq!(indexed_output_path("out-{}.png", 3), "out-3.png");
+        assert_eq!(indexed_output_path("out.json", 0), "out.0.json");
+        assert_eq!(indexed_output_path("dir/out.json", 1), "dir/out.1.json");
+        assert_eq!(indexed_output_path("out", 2), "out.2");
+    }
+
+    #[test]
+    fn test_run_batch_continues_after_failed_input() {
+        // Stands in for the API, failing the call for the input "fail"
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", server.server_addr());
+        let api = thread::spawn(move || {
+            for _ in 0..3 {
+                let mut request = server.recv().unwrap();
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                let (status, response) = if body.contains("fail") {
+                    (400, r#"{"error":{"message":"input failed"}}"#.to_string())
+                } else {
+                    let metadata = r#"{"content_type":"json","duration":0.1}"#;
+                    let response = format!(r#"{{"result":{},"metadata":{}}}"#, body, metadata);
+                    (200, response)
+                };
+                let response = tiny_http::Response::from_string(response).with_status_code(status);
+                request.respond(response).unwrap();
+            }
+        });
+
+        let output = env::temp_dir().join(format!("mia-run-batch-{}-{{}}.json", process::id()));
+        let args = Args {
+            cmd_run: true,
+            arg_algorithm: "demo/Hello".into(),
+            flag_response_body: false,
+            flag_response: false,
+            flag_silence: true,
+            flag_debug: false,
+            flag_no_debug: true,
+            flag_output: Some(output.to_string_lossy().into_owned()),
+            flag_timeout: None,
+            flag_parallel: 2,
+            flag_jsonl: None,
+            flag_continue_on_error: false,
+        };
+        let run = Run {
+            client: Algorithmia::client_with_url("simTestKey", &*base_url).unwrap(),
+        };
+        let inputs = vec![
+            InputData::Json("1".into()),
+            InputData::Json("\"fail\"".into()),
+            InputData::Json("3".into()),
+        ];
+
+        assert_eq!(run.run_batch(&args, inputs, false), vec![1]);
+        api.join().unwrap();
+
+        // The inputs after the failure were still called and their results written
+        let result = |index| {
+            let path = indexed_output_path(&output.to_string_lossy(), index);
+            let result = fs::read_to_string(&path).unwrap();
+            let _ = fs::remove_file(&path);
+            result
+        };
+        assert_eq!(result(0).trim(), "1");
+        assert_eq!(result(2).trim(), "3");
+        let _ = fs::remove_file(indexed_output_path(&output.to_string_lossy(), 1));
     }
 }
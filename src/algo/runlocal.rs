@@ -0,0 +1,139 @@
+use super::local::LocalAlgo;
+use super::{display_response_body, split_args, ResponseConfig};
+use crate::{color_choice, CmdRunner, GRAY};
+use docopt::Docopt;
+use std::io::Write;
+use std::path::Path;
+use std::vec::IntoIter;
+use termcolor::{ColorSpec, StandardStream, WriteColor};
+
+static USAGE: &str = r##"Usage:
+  mia runlocal [options] [<path>]
+
+  Builds and runs an algorithm from a local checkout (e.g. one created by 'mia clone')
+  without pushing it to the git server. The language is detected from algorithmia.conf
+  in <path> (defaults to the current directory).
+
+  Python algorithms are supported directly. Other languages are run using the bin/build
+  and bin/pipe scripts of the algorithm repo if present.
+
+  Input Data Options:
+    There are option variants for specifying the type and source of input data.
+    If <file> is '-', then input data will be read from STDIN.
+
+    Auto-Detect Data:
+      -d, --data <data>             If the data parses as JSON, assume JSON, else if the data
+                                      is valid UTF-8, assume text, else assume binary
+      -D, --data-file <file>        Same as --data, but the input data is read from a file
+
+    JSON Data:
+      -j, --json <data>             Algorithm input data as JSON (application/json)
+      -J, --json-file <file>        Same as --json, but the input data is read from a file
+
+    Text Data:
+      -t, --text <data>             Algorithm input data as text (text/plain)
+      -T, --text-file <file>        Same as --text, but the input data is read from a file
+
+    Binary Data:
+      -b, --binary <data>           Algorithm input data as binary (application/octet-stream)
+      -B, --binary-file <file>      Same as --data, but the input data is read from a file
+
+
+  Output Options:
+    By default, only the algorithm result is printed to STDOUT while build output and the
+    algorithm's STDOUT are printed to STDERR in real-time.
+
+    --response-body                 Print the algorithm response body (replaces result)
+    -s, --silence                   Suppress any output not explicitly requested (except result)
+    -o, --output <file>             Print result to a file
+
+  Other Options:
+    --no-build                      Skip building the algorithm
+
+  Examples:
+    mia runlocal -d '79'                                 Run algorithm in the current directory
+    mia runlocal bcrypt -D input.json                    Run algorithm cloned into ./bcrypt
+    mia runlocal --no-build -d '79'                      Run algorithm again without rebuilding
+"##;
+
+#[derive(RustcDecodable, Debug)]
+struct Args {
+    arg_path: Option<String>,
+    flag_response_body: bool,
+    flag_silence: bool,
+    flag_output: Option<String>,
+    flag_no_build: bool,
+}
+
+#[derive(Default)]
+pub struct RunLocal;
+impl CmdRunner for RunLocal {
+    fn get_usage() -> &'static str {
+        USAGE
+    }
+
+    fn cmd_main(&self, argv: IntoIter<String>) {
+        // We need to preprocess input args before giving other args to Docopt
+        let (mut input_args, other_args) = split_args(argv, USAGE);
+
+        // Parse the remaining args with Docopt
+        let args: Args = Docopt::new(USAGE)
+            .and_then(|d| d.argv(other_args).decode())
+            .unwrap_or_else(|e| e.exit());
+
+        match input_args.len() {
+            1 => (),
+            0 => quit_msg!("Must specify an input data option\n\n{}", USAGE),
+            _ => quit_msg!("runlocal accepts only a single input data option"),
+        };
+
+        let path = args.arg_path.as_deref().unwrap_or(".");
+        let algo = LocalAlgo::from_dir(Path::new(path)).unwrap_or_else(|err| quit_msg!("{}", err));
+
+        if !args.flag_no_build {
+            self.status(
+                &args,
+                &format!("Building {} ({})", algo.algoname(), algo.language()),
+            );
+            if let Err(err) = algo.build(!args.flag_silence) {
+                quit_msg!("{}", err);
+            }
+        }
+
+        self.status(&args, &format!("Running {}", algo.algoname()));
+        let mut process = algo
+            .start(!args.flag_silence)
+            .unwrap_or_else(|err| quit_msg!("{}", err));
+        let json_response = process
+            .call(&input_args.remove(0))
+            .unwrap_or_else(|err| quit_msg!("Error calling algorithm: {}", err));
+
+        // The algorithm's stdout was already streamed to stderr, so it isn't repeated here
+        let config = ResponseConfig {
+            flag_response_body: args.flag_response_body,
+            flag_response: false,
+            flag_silence: args.flag_silence,
+            flag_debug: false,
+            flag_output: args.flag_output,
+        };
+
+        if !display_response_body(&json_response, None, config) {
+            ::std::process::exit(1);
+        }
+    }
+}
+
+impl RunLocal {
+    pub fn new() -> Self {
+        RunLocal
+    }
+
+    fn status(&self, args: &Args, msg: &str) {
+        if !args.flag_silence {
+            let mut t_err = StandardStream::stderr(color_choice());
+            let _ = t_err.set_color(ColorSpec::new().set_fg(Some(GRAY)));
+            let _ = writeln!(t_err, "{}", msg);
+            let _ = t_err.reset();
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use super::local::{pipe_request, LocalAlgo, LocalProcess};
+use super::InputData;
+use crate::{color_choice, CmdRunner, BRIGHT_RED, GRAY};
+use docopt::Docopt;
+use rustc_serialize::json::Json;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::time::Instant;
+use std::vec::IntoIter;
+use termcolor::{Color, ColorSpec, StandardStream, WriteColor};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+static USAGE: &str = r##"Usage:
+  mia serve [options] [<path>]
+
+  Builds an algorithm from a local checkout (e.g. one created by 'mia clone') and serves it
+  over HTTP at /v1/algo/<user>/<algo>[/<version>] with the same request and response format
+  as the Algorithmia API. The language is detected from algorithmia.conf in <path> (defaults
+  to the current directory).
+
+  To call the local algorithm with 'mia run' or an Algorithmia client, configure a profile
+  with the server as its API endpoint (any API key is accepted), e.g. in ~/.algorithmia/config:
+
+    [profiles.local]
+    api_key = "simLocalServerKey"
+    api_server = "http://localhost:9999"
+
+  Requests are handled one at a time. The algorithm's STDOUT is printed to STDERR.
+
+  Options:
+    -p, --port <port>               Port to listen on [default: 9999]
+    --host <host>                   Address to bind to [default: 127.0.0.1]
+    --no-build                      Skip building the algorithm
+    -s, --silence                   Suppress build output, algorithm STDOUT, and request logging
+
+  Examples:
+    mia serve                                            Serve algorithm in the current directory
+    mia serve bcrypt --port 8080                         Serve algorithm cloned into ./bcrypt
+    mia run anowell/bcrypt -d 'password' --profile local Call the served algorithm
+"##;
+
+#[derive(RustcDecodable, Debug)]
+struct Args {
+    arg_path: Option<String>,
+    flag_port: u16,
+    flag_host: String,
+    flag_no_build: bool,
+    flag_silence: bool,
+}
+
+#[derive(Default)]
+pub struct Serve;
+impl CmdRunner for Serve {
+    fn get_usage() -> &'static str {
+        USAGE
+    }
+
+    fn cmd_main(&self, argv: IntoIter<String>) {
+        let args: Args = Docopt::new(USAGE)
+            .and_then(|d| d.argv(argv).decode())
+            .unwrap_or_else(|e| e.exit());
+
+        let path = args.arg_path.as_deref().unwrap_or(".");
+        let algo = LocalAlgo::from_dir(Path::new(path)).unwrap_or_else(|err| quit_msg!("{}", err));
+
+        if !args.flag_no_build {
+            log(
+                &args,
+                GRAY,
+                &format!("Building {} ({})", algo.algoname(), algo.language()),
+            );
+            if let Err(err) = algo.build(!args.flag_silence) {
+                quit_msg!("{}", err);
+            }
+        }
+
+        let addr = format!("{}:{}", args.flag_host, args.flag_port);
+        let server = Server::http(&*addr)
+            .unwrap_or_else(|err| quit_msg!("Unable to listen on {}: {}", addr, err));
+        let mut process = algo
+            .start(!args.flag_silence)
+            .unwrap_or_else(|err| quit_msg!("{}", err));
+
+        log(
+            &args,
+            GRAY,
+            &format!(
+                "Serving {} at http://{}/v1/algo/<user>/{}",
+                algo.algoname(),
+                addr,
+                algo.algoname()
+            ),
+        );
+
+        for request in server.incoming_requests() {
+            let start = Instant::now();
+            let summary = format!("{} {}", request.method(), request.url());
+            let (status, algo_failed) = self.handle(&algo, &mut process, request);
+            let color = if status < 400 { GRAY } else { BRIGHT_RED };
+            log(
+                &args,
+                color,
+                &format!(
+                    "{} -> {} ({:.1} seconds)",
+                    summary,
+                    status,
+                    start.elapsed().as_secs_f32()
+                ),
+            );
+
+            // Restart the algorithm if it died handling the request
+            if algo_failed {
+                process = algo
+                    .start(!args.flag_silence)
+                    .unwrap_or_else(|err| quit_msg!("{}", err));
+            }
+        }
+    }
+}
+
+impl Serve {
+    pub fn new() -> Self {
+        Serve
+    }
+
+    // Responds to a single request, returning the response status
+    //   and whether the algorithm process failed to handle it
+    fn handle(
+        &self,
+        algo: &LocalAlgo,
+        process: &mut LocalProcess,
+        mut request: Request,
+    ) -> (u16, bool) {
+        let mut algo_failed = false;
+        let (status, body) = match route(algo.algoname(), &request) {
+            Err((status, msg)) => (status, error_body(msg)),
+            Ok(()) => {
+                let mut bytes = Vec::new();
+                match request.as_reader().read_to_end(&mut bytes) {
+                    Err(err) => (400, error_body(format!("Error reading request: {}", err))),
+                    Ok(_) => {
+                        let input = input_data(&content_type(&request), bytes);
+                        // Invalid input is rejected before it reaches the algorithm, so only
+                        //   errors of the process itself (e.g. it exited) restart it
+                        match pipe_request(&input) {
+                            Err(err) => (400, error_body(err.to_string())),
+                            Ok(pipe_input) => match process.send(&pipe_input) {
+                                Ok(json) => {
+                                    // Algorithm errors are reported as 400s like the Algorithmia API
+                                    let status = match Json::from_str(&json) {
+                                        Ok(ref res) if res.find("error").is_some() => 400,
+                                        _ => 200,
+                                    };
+                                    (status, json)
+                                }
+                                Err(err) => {
+                                    algo_failed = true;
+                                    (500, error_body(format!("Error calling algorithm: {}", err)))
+                                }
+                            },
+                        }
+                    }
+                }
+            }
+        };
+
+        let json_header =
+            Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+        let response = Response::from_string(body)
+            .with_status_code(status)
+            .with_header(json_header);
+        if let Err(err) = request.respond(response) {
+            eprintln_red!("Error sending response: {}", err);
+        }
+        (status, algo_failed)
+    }
+}
+
+// Only POST /v1/algo/<user>/<algo>[/<version>] is routed to the algorithm
+fn route(algoname: &str, request: &Request) -> Result<(), (u16, String)> {
+    let path = request.url().split('?').next().unwrap_or("");
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match segments.as_slice() {
+        ["v1", "algo", _user, name] | ["v1", "algo", _user, name, _] => {
+            if !name.eq_ignore_ascii_case(algoname) {
+                Err((404, format!("algorithm {} not found", path)))
+            } else if *request.method() != Method::Post {
+                Err((405, format!("method {} not allowed", request.method())))
+            } else {
+                Ok(())
+            }
+        }
+        _ => Err((404, format!("{} not found", path))),
+    }
+}
+
+fn content_type(request: &Request) -> String {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Content-Type"))
+        .map(|h| h.value.as_str().to_lowercase())
+        .unwrap_or_default()
+}
+
+// Maps the request Content-Type to InputData the same way the Algorithmia API does
+fn input_data(content_type: &str, bytes: Vec<u8>) -> InputData {
+    let mime = content_type.split(';').next().unwrap_or("").trim();
+    match mime {
+        "application/json" => match String::from_utf8(bytes) {
+            Ok(json) => InputData::Json(json),
+            Err(not_utf8) => InputData::Binary(not_utf8.into_bytes()),
+        },
+        "text/plain" => match String::from_utf8(bytes) {
+            Ok(text) => InputData::Text(text),
+            Err(not_utf8) => InputData::Binary(not_utf8.into_bytes()),
+        },
+        "application/octet-stream" => InputData::Binary(bytes),
+        _ => InputData::auto(&mut &*bytes),
+    }
+}
+
+fn error_body(message: String) -> String {
+    let mut error = BTreeMap::new();
+    error.insert("message".to_string(), Json::String(message));
+    let mut body = BTreeMap::new();
+    body.insert("error".to_string(), Json::Object(error));
+    Json::Object(body).to_string()
+}
+
+fn log(args: &Args, color: Color, msg: &str) {
+    if !args.flag_silence {
+        let mut t_err = StandardStream::stderr(color_choice());
+        let _ = t_err.set_color(ColorSpec::new().set_fg(Some(color)));
+        let _ = writeln!(t_err, "{}", msg);
+        let _ = t_err.reset();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tiny_http::TestRequest;
+
+    #[test]
+    fn test_route() {
+        let route = |method, path| {
+            route(
+                "Hello",
+                &TestRequest::new()
+                    .with_method(method)
+                    .with_path(path)
+                    .into(),
+            )
+        };
+        assert_eq!(route(Method::Post, "/v1/algo/demo/hello"), Ok(()));
+        assert_eq!(
+            route(Method::Post, "/v1/algo/demo/Hello/1.0.0?timeout=30"),
+            Ok(())
+        );
+        assert_eq!(
+            route(Method::Post, "/v1/algo/demo/other").unwrap_err().0,
+            404
+        );
+        assert_eq!(
+            route(Method::Post, "/v1/data/.my/hello").unwrap_err().0,
+            404
+        );
+        assert_eq!(
+            route(Method::Post, "/v1/algo/demo/hello/1.0.0/extra")
+                .unwrap_err()
+                .0,
+            404
+        );
+        assert_eq!(
+            route(Method::Get, "/v1/algo/demo/hello").unwrap_err().0,
+            405
+        );
+    }
+
+    #[test]
+    fn test_input_data() {
+        let input = |content_type, bytes: &[u8]| input_data(content_type, bytes.to_vec());
+        assert_eq!(
+            input("application/json", b"[1]"),
+            InputData::Json("[1]".into())
+        );
+        assert_eq!(
+            input("application/json; charset=utf-8", b"\"hi\""),
+            InputData::Json("\"hi\"".into())
+        );
+        assert_eq!(input("text/plain", b"[1]"), InputData::Text("[1]".into()));
+        assert_eq!(
+            input("application/octet-stream", b"[1]"),
+            InputData::Binary(b"[1]".to_vec())
+        );
+        assert_eq!(
+            input("application/json", b"\xff"),
+            InputData::Binary(vec![0xff])
+        );
+
+        // Without a known Content-Type, the type is detected from the body
+        assert_eq!(
+            input("", b"{\"a\": 1}"),
+            InputData::Json("{\"a\": 1}".into())
+        );
+        assert_eq!(input("", b"hello"), InputData::Text("hello".into()));
+        assert_eq!(
+            input("image/png", b"\x89PNG\xff"),
+            InputData::Binary(b"\x89PNG\xff".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_error_body() {
+        let body = Json::from_str(&error_body("algorithm failed".into())).unwrap();
+        assert_eq!(
+            body.find_path(&["error", "message"])
+                .and_then(Json::as_string),
+            Some("algorithm failed")
+        );
+        assert_eq!(body.as_object().map(|body| body.len()), Some(1));
+    }
+}
@@ -0,0 +1,257 @@
+use crate::api::ApiClient;
+use crate::config::Profile;
+use crate::CmdRunner;
+use docopt::Docopt;
+use rustc_serialize::json::Json;
+use std::collections::BTreeMap;
+use std::vec::IntoIter;
+
+static USAGE: &str = r##"Usage:
+  mia view [options] <algorithm>
+  mia info [options] <algorithm>
+
+  <algorithm> syntax: USERNAME/ALGONAME[/VERSION]
+  Shows details of the latest published version unless VERSION is specified.
+
+  Options:
+    --json                          Print the algorithm details as JSON
+    --versions <n>                  Max number of published versions to list [default: 10]
+
+  Examples:
+    mia view kenny/factor                                Show details and recent versions
+    mia view kenny/factor/0.1.0                          Show details of a specific version
+    mia info kenny/factor --json                         Print details as JSON
+"##;
+
+#[derive(RustcDecodable, Debug)]
+struct Args {
+    arg_algorithm: String,
+    flag_json: bool,
+    flag_versions: u32,
+}
+
+pub struct View {
+    client: ApiClient,
+}
+impl CmdRunner for View {
+    fn get_usage() -> &'static str {
+        USAGE
+    }
+
+    fn cmd_main(&self, argv: IntoIter<String>) {
+        let args: Args = Docopt::new(USAGE)
+            .and_then(|d| d.argv(argv).decode())
+            .unwrap_or_else(|e| e.exit());
+
+        let algo = args.arg_algorithm.trim_start_matches("algo://");
+        let parts: Vec<&str> = algo.trim_matches('/').split('/').collect();
+        let (algo_path, version) = match parts.len() {
+            2 => (parts.join("/"), None),
+            3 => (format!("{}/{}", parts[0], parts[1]), Some(parts[2])),
+            _ => quit_msg!("Invalid algorithm '{}'\n\n{}", args.arg_algorithm, USAGE),
+        };
+
+        let info = match version {
+            Some(v) => self.get(&format!("/v1/algorithms/{}/versions/{}", algo_path, v)),
+            None => self.get(&format!("/v1/algorithms/{}", algo_path)),
+        };
+        // Listing versions is best-effort since the details are still useful without them
+        let versions = self
+            .client
+            .get(&format!(
+                "/v1/algorithms/{}/versions?published=true&limit={}",
+                algo_path, args.flag_versions
+            ))
+            .unwrap_or(Json::Null);
+        let versions = versions
+            .find("results")
+            .and_then(Json::as_array)
+            .cloned()
+            .unwrap_or_default();
+
+        if args.flag_json {
+            let mut obj = BTreeMap::new();
+            obj.insert("algorithm".to_string(), info);
+            obj.insert("versions".to_string(), Json::Array(versions));
+            println!("{}", Json::Object(obj).pretty());
+        } else {
+            print_info(&algo_path, &info, &versions);
+        }
+    }
+}
+
+impl View {
+    pub fn new(profile: Profile) -> Self {
+        View {
+            client: profile.api_client(),
+        }
+    }
+
+    fn get(&self, path: &str) -> Json {
+        self.client
+            .get(path)
+            .unwrap_or_else(|err| quit_msg!("Error getting algorithm details: {}", err))
+    }
+}
+
+fn print_info(algo_path: &str, info: &Json, versions: &[Json]) {
+    for (label, value) in info_rows(algo_path, info) {
+        println!("{:<15}{}", format!("{}:", label), value);
+    }
+
+    println!("\nPublished versions:");
+    if versions.is_empty() {
+        println!("  (none)");
+    }
+    for v in versions {
+        println!("  {}", version_row(v));
+    }
+}
+
+fn info_rows(algo_path: &str, info: &Json) -> Vec<(&'static str, String)> {
+    let callability = field(info, &["settings", "algorithm_callability"]);
+    let source = field(info, &["settings", "source_visibility"]);
+    let language = field(info, &["settings", "language"]);
+    let env = field(info, &["settings", "environment"]);
+    let royalty = info
+        .find_path(&["settings", "royalty_microcredits"])
+        .and_then(Json::as_u64);
+
+    vec![
+        ("Algorithm", algo_path.to_owned()),
+        ("Name", field(info, &["details", "label"])),
+        ("Summary", field(info, &["details", "summary"])),
+        (
+            "Version",
+            field(info, &["version_info", "semantic_version"]),
+        ),
+        ("Git hash", field(info, &["version_info", "git_hash"])),
+        ("Language", format!("{} (environment: {})", language, env)),
+        (
+            "Visibility",
+            format!("{} callability, {} source", callability, source),
+        ),
+        (
+            "Royalty",
+            match royalty {
+                Some(0) | None => "none".to_owned(),
+                Some(r) => format!("{} microcredits per call", r),
+            },
+        ),
+        ("License", field(info, &["settings", "license"])),
+        ("Network", field(info, &["settings", "network_access"])),
+        (
+            "Build",
+            format!(
+                "{} ({})",
+                field(info, &["build", "status"]),
+                field(info, &["build", "commit_sha"])
+            ),
+        ),
+        (
+            "Sample input",
+            field(info, &["version_info", "sample_input"]),
+        ),
+        (
+            "Sample output",
+            field(info, &["version_info", "sample_output"]),
+        ),
+    ]
+}
+
+fn version_row(version: &Json) -> String {
+    format!(
+        "{:<12}{:<42}{}",
+        field(version, &["version_info", "semantic_version"]),
+        field(version, &["version_info", "git_hash"]),
+        field(version, &["version_info", "release_notes"]),
+    )
+}
+
+// Renders a nested field for display, using '-' for missing fields
+fn field(json: &Json, path: &[&str]) -> String {
+    match json.find_path(path) {
+        None | Some(Json::Null) => "-".to_owned(),
+        Some(Json::String(s)) => s.replace('\n', " "),
+        Some(other) => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Trimmed from a GET /v1/algorithms/:user/:algo/versions/:version response
+    static ALGORITHM: &str = r#"{
+        "id": "d1e1f9d4-7f8a-4a4b-9a8e-3b2f5e0c6a11",
+        "name": "Hello",
+        "details": {
+            "label": "Hello World",
+            "summary": "Says hello\nto anyone"
+        },
+        "settings": {
+            "algorithm_callability": "public",
+            "source_visibility": "closed",
+            "language": "python3-1",
+            "environment": "cpu",
+            "license": "apl",
+            "network_access": "full",
+            "pipeline_enabled": false,
+            "royalty_microcredits": 15
+        },
+        "version_info": {
+            "semantic_version": "0.2.0",
+            "git_hash": "0a1b2c3d4e5f60718293a4b5c6d7e8f901234567",
+            "release_notes": "Adds greetings",
+            "sample_input": "\"world\""
+        },
+        "build": {
+            "build_id": "8f3e6c1a-0d2b-4c5e-9f7a-1b2c3d4e5f60",
+            "status": "succeeded",
+            "commit_sha": "0a1b2c3d4e5f60718293a4b5c6d7e8f901234567",
+            "version_info": {"semantic_version": "0.2.0"}
+        },
+        "compilation": {"successful": true, "output": ""},
+        "self_link": "https://api.algorithmia.com/v1/algorithms/demo/Hello/versions/0.2.0",
+        "resource_type": "algorithm"
+    }"#;
+
+    #[test]
+    fn test_info_rows() {
+        let info = Json::from_str(ALGORITHM).unwrap();
+        let rows: BTreeMap<_, _> = info_rows("demo/Hello/0.2.0", &info).into_iter().collect();
+        assert_eq!(rows["Algorithm"], "demo/Hello/0.2.0");
+        assert_eq!(rows["Name"], "Hello World");
+        assert_eq!(rows["Summary"], "Says hello to anyone");
+        assert_eq!(rows["Version"], "0.2.0");
+        assert_eq!(rows["Language"], "python3-1 (environment: cpu)");
+        assert_eq!(rows["Visibility"], "public callability, closed source");
+        assert_eq!(rows["Royalty"], "15 microcredits per call");
+        assert_eq!(rows["Network"], "full");
+        assert_eq!(
+            rows["Build"],
+            "succeeded (0a1b2c3d4e5f60718293a4b5c6d7e8f901234567)"
+        );
+        assert_eq!(rows["Sample input"], "\"world\"");
+        assert_eq!(rows["Sample output"], "-");
+
+        assert_eq!(
+            version_row(&info),
+            format!(
+                "{:<12}{:<42}{}",
+                "0.2.0", "0a1b2c3d4e5f60718293a4b5c6d7e8f901234567", "Adds greetings"
+            )
+        );
+    }
+
+    #[test]
+    fn test_field() {
+        let info = Json::from_str(r#"{"settings": {"royalty_microcredits": 0, "license": null}}"#)
+            .unwrap();
+        assert_eq!(field(&info, &["settings", "royalty_microcredits"]), "0");
+        assert_eq!(field(&info, &["settings", "license"]), "-");
+        assert_eq!(field(&info, &["settings", "missing"]), "-");
+        let rows: BTreeMap<_, _> = info_rows("demo/Hello", &info).into_iter().collect();
+        assert_eq!(rows["Royalty"], "none");
+    }
+}
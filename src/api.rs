@@ -0,0 +1,113 @@
+//! Minimal client for Algorithmia API endpoints not covered by the `algorithmia` crate
+//!
+//! Requests and responses are JSON, authenticated the same way as the `algorithmia` client.
+//! The `algorithmia` client's own HTTP client is private, so this uses the same version of
+//! `reqwest` directly rather than adding another HTTP stack.
+use crate::DynError;
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::header::{HeaderMap, AUTHORIZATION, CONTENT_TYPE, RANGE, USER_AGENT};
+use reqwest::Method;
+use rustc_serialize::json::Json;
+use std::io::Read;
+use std::time::Duration;
+
+// Timeout of JSON requests, including reading the response. Streamed downloads have none,
+//   since reading a large body can take any amount of time.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Clone)]
+pub struct ApiClient {
+    base_url: String,
+    api_key: String,
+    client: Client,
+}
+
+impl ApiClient {
+    pub fn new(base_url: &str, api_key: &str) -> ApiClient {
+        ApiClient {
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            api_key: api_key.to_owned(),
+            client: Client::builder()
+                .timeout(None)
+                .connect_timeout(CONNECT_TIMEOUT)
+                .build()
+                .expect("Failed to build HTTP client"),
+        }
+    }
+
+    /// GET a JSON resource, e.g. `/v1/algorithms/anowell/Pinky`
+    pub fn get(&self, path: &str) -> Result<Json, DynError> {
+        self.send(self.request(Method::GET, path).timeout(REQUEST_TIMEOUT))
+    }
+
+    /// POST a JSON body, returning the JSON response
+    pub fn post(&self, path: &str, body: &Json) -> Result<Json, DynError> {
+        self.send(self.json_request(Method::POST, path, body))
+    }
+
+    /// PATCH a JSON body, returning the JSON response
+    pub fn patch(&self, path: &str, body: &Json) -> Result<Json, DynError> {
+        self.send(self.json_request(Method::PATCH, path, body))
+    }
+
+    /// HEAD a resource, returning the response headers
+    pub fn head(&self, path: &str) -> Result<HeaderMap, DynError> {
+        let res = self
+            .request(Method::HEAD, path)
+            .timeout(REQUEST_TIMEOUT)
+            .send()?;
+        match res.status() {
+            status if status.is_success() => Ok(res.headers().clone()),
+            status => Err(format!("API request failed ({})", status).into()),
+        }
+    }
+
+    /// GET a resource starting at byte `offset` using a Range request, returning the response to
+    /// stream regardless of its status. Servers without Range support return the whole resource.
+    pub fn get_from(&self, path: &str, offset: u64) -> Result<Response, DynError> {
+        let mut req = self.request(Method::GET, path);
+        if offset > 0 {
+            req = req.header(RANGE, format!("bytes={}-", offset));
+        }
+        Ok(req.send()?)
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.client
+            .request(method, &format!("{}{}", self.base_url, path))
+            .header(AUTHORIZATION, format!("Simple {}", self.api_key))
+            .header(
+                USER_AGENT,
+                crate::version::VERSION.lines().next().unwrap_or("mia"),
+            )
+    }
+
+    fn json_request(&self, method: Method, path: &str, body: &Json) -> RequestBuilder {
+        self.request(method, path)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .timeout(REQUEST_TIMEOUT)
+    }
+
+    fn send(&self, req: RequestBuilder) -> Result<Json, DynError> {
+        let mut res = req.send()?;
+        let mut body = String::new();
+        res.read_to_string(&mut body)?;
+
+        let status = res.status();
+        let parsed = match body.trim() {
+            "" => Ok(Json::Null),
+            b => Json::from_str(b),
+        };
+        match (status.is_success(), parsed) {
+            (true, Ok(json)) => Ok(json),
+            (true, Err(err)) => Err(format!("Failed to parse API response: {}", err).into()),
+            (false, Ok(json)) => match json.find_path(&["error", "message"]) {
+                Some(Json::String(msg)) => Err(format!("{} ({})", msg, status).into()),
+                _ => Err(format!("API request failed ({})", status).into()),
+            },
+            (false, Err(_)) => Err(format!("API request failed ({})", status).into()),
+        }
+    }
+}
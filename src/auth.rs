@@ -1,4 +1,6 @@
 use crate::config::{self, Config, Profile};
+use crate::credential::{self, ApiKeySource};
+use crate::profile::mask_api_key;
 use crate::{CmdRunner, DynError};
 use docopt::Docopt;
 use rpassword;
@@ -8,11 +10,38 @@ use url::Url;
 
 static USAGE: &'static str = r##"
 Usage:
-  mia auth [--profile <name>]
+  mia auth [options] [--profile <name>]
+  mia auth --check [--profile <name>]
+  mia whoami [--profile <name>]
 
   Interactively prompts for authentication credentials. If no profile is specified,
-  the changes will apply to the 'default' profile. To use a non-default profile for
-  other mia commands, use the --profile <profile> option.
+  the changes will apply to the default profile ('default', unless changed with
+  'mia profile default'). To use a non-default profile for other mia commands, use
+  the --profile <profile> option.
+
+  Before saving, the credentials are checked by listing data://.my with them. If that fails,
+  the error is shown and you are asked whether to save the profile anyway. 'mia auth --check'
+  (or 'mia whoami') checks the credentials of an existing profile, including any overrides
+  from ALGORITHMIA_* environment variables.
+
+  For scripts (e.g. CI or Docker builds), the prompts can be replaced by options. Nothing is
+  prompted for when the API key is read from stdin or a command, and the endpoint defaults to
+  algorithmia.com.
+
+  Options:
+    --api-key-stdin           Read the API key from the first line of stdin
+    --endpoint <url>          Algorithmia endpoint, where the API and Git servers are its 'api.'
+                                and 'git.' subdomains [e.g. https://algorithmia.com]
+    --api-server <url>        API server, for endpoints that don't follow that convention
+                                (instead of --endpoint)
+    --git-server <url>        Git server, if it isn't the 'git.' subdomain of the endpoint
+    --api-key-store <store>   Where to save the API key: config (the default), or keyring for
+                                the OS secret store (Secret Service via 'secret-tool' on Linux,
+                                or the macOS Keychain)
+    --api-key-cmd <cmd>       Instead of saving the API key, run this command to print it
+                                whenever it's needed (e.g. "pass show algorithmia")
+    --no-check                Save the profile without checking the credentials with the server,
+                                which is otherwise required without prompts
 
   Profile configuration is stored in $HOME/.algorithmia (Unix/Linux) or
   %LOCALAPPDATA%/Algorithmia (Windows) in the following TOML format:
@@ -21,12 +50,32 @@ Usage:
 
     [profiles.default]
     sim_key = "sim1234567890abcdef"
+
+  Instead of the API key, the profile can hold a reference to it:
+
+    [profiles.work]
+    api_key_keyring = "mia-17f3a2b4c5d6e7f8"   # saved by --api-key-store keyring
+    api_key_cmd = "pass show algorithmia"      # saved by --api-key-cmd
+
+  Examples:
+    echo "$ALGORITHMIA_API_KEY" | mia auth --api-key-stdin
+    mia auth --profile work --api-key-store keyring
+    mia auth --api-key-stdin --endpoint https://methods.example.com < key.txt
 "##;
 
 #[derive(RustcDecodable, Debug)]
 struct Args {
     // commented out because profile is stripped by `main` and passed directly into `new`
-// arg_profile: Option<String>,
+    // arg_profile: Option<String>,
+    flag_api_key_stdin: bool,
+    flag_endpoint: Option<String>,
+    flag_api_server: Option<String>,
+    flag_git_server: Option<String>,
+    flag_api_key_store: Option<String>,
+    flag_api_key_cmd: Option<String>,
+    flag_no_check: bool,
+    flag_check: bool,
+    cmd_whoami: bool,
 }
 
 pub struct Auth {
@@ -38,11 +87,21 @@ impl CmdRunner for Auth {
     }
 
     fn cmd_main(&self, argv: IntoIter<String>) {
-        let _args: Args = Docopt::new(USAGE)
+        let args: Args = Docopt::new(USAGE)
             .and_then(|d| d.argv(argv).decode())
             .unwrap_or_else(|e| e.exit());
 
-        Auth::prompt_for_auth(&self.profile);
+        if args.flag_check || args.cmd_whoami {
+            Auth::check_profile(&self.profile);
+        }
+        if args.flag_api_key_cmd.is_some() && args.flag_api_key_stdin {
+            quit_msg!("Use either --api-key-stdin or --api-key-cmd, not both");
+        }
+        if args.flag_api_key_stdin || args.flag_api_key_cmd.is_some() {
+            Auth::auth_from_args(&self.profile, &args);
+        } else {
+            Auth::prompt_for_auth(&self.profile, &args);
+        }
     }
 }
 
@@ -53,29 +112,28 @@ impl Auth {
         }
     }
 
-    fn prompt_for_auth(profile_name: &str) {
+    fn prompt_for_auth(profile_name: &str, args: &Args) {
+        let store = key_store(profile_name, args);
         println!("Configuring authentication for '{}' profile", profile_name);
 
-        // Handle Endpoint URL
-        print!(
-            "Enter Algorithmia Endpoint [default={}]: ",
-            config::DEFAULT_ENDPOINT
-        );
-        let _ = io::stdout().flush();
+        // Handle Endpoint URL, unless it was given as an option
+        let (api_server, git_server) = match servers_from_args(args) {
+            Some(servers) => servers,
+            None => {
+                print!(
+                    "Enter Algorithmia Endpoint [default={}]: ",
+                    config::DEFAULT_ENDPOINT
+                );
+                let _ = io::stdout().flush();
 
-        let endpoint = match prompt_for_url() {
-            None => Url::parse(config::DEFAULT_ENDPOINT).unwrap(),
-            Some(mut u) => {
-                // Special handling of 'api.' as it's still likely that many enter the API endpoint
-                // instead of the parent domain.
-                remove_subdomain(&mut u, "api");
-                u
+                let endpoint = match prompt_for_url() {
+                    None => Url::parse(config::DEFAULT_ENDPOINT).unwrap(),
+                    Some(u) => u,
+                };
+                endpoint_servers(endpoint)
             }
         };
 
-        let api_server = prepend_subdomain(&endpoint, "api");
-        let git_server = prepend_subdomain(&endpoint, "git");
-
         // Handle API Key
         print!("Enter API Key (starts with 'sim'): ");
         let _ = io::stdout().flush();
@@ -84,30 +142,251 @@ impl Auth {
             Ok(key) => key,
             Err(err) => quit_err!("Cannot read password: {}", err),
         };
-        if api_key.len() == 28 && api_key.starts_with("sim") {
-            let mut config = Config::read_config().unwrap_or_else(Config::default);
-            let profile = Profile::new(api_key.into(), Some(api_server), Some(git_server));
-
-            config.update_profile(profile_name.into(), profile);
-            config.write_config();
-
-            if profile_name == "default" {
-                println!("Profile is ready to use. Try 'mia ls'");
-            } else {
-                println!(
-                    "Profile is ready to use. Try 'mia ls --profile {}'",
+        validate(profile_name, &api_key, &api_server, &git_server, args, true);
+        save_profile(profile_name, api_key, store, api_server, git_server);
+    }
+
+    // Configures a profile without prompting, for scripts
+    fn auth_from_args(profile_name: &str, args: &Args) {
+        let (api_server, git_server) = servers_from_args(args)
+            .unwrap_or_else(|| endpoint_servers(Url::parse(config::DEFAULT_ENDPOINT).unwrap()));
+
+        let store = key_store(profile_name, args);
+
+        // A command is run once here, so that a key that doesn't look right isn't saved
+        let api_key = match args.flag_api_key_cmd {
+            Some(ref cmd) => ApiKeySource::Command(cmd.clone())
+                .read()
+                .unwrap_or_else(|err| quit_msg!("{}", err)),
+            None => {
+                let mut line = String::new();
+                let stdin = io::stdin();
+                match stdin.lock().read_line(&mut line) {
+                    Ok(0) => quit_msg!("No API Key provided on stdin"),
+                    Ok(_) => (),
+                    Err(err) => quit_err!("Cannot read API Key from stdin: {}", err),
+                }
+                line.trim().to_owned()
+            }
+        };
+        validate(
+            profile_name,
+            &api_key,
+            &api_server,
+            &git_server,
+            args,
+            false,
+        );
+        save_profile(profile_name, api_key, store, api_server, git_server);
+    }
+
+    // Checks the credentials of an existing profile, exiting with an error if they don't work
+    fn check_profile(profile_name: &str) -> ! {
+        let profile = Profile::lookup(profile_name);
+        println!("Profile:    {}", profile_name);
+        println!("API server: {}", profile.api_server());
+        println!("API key:    {}", mask_api_key(profile.api_key()));
+        match check_credentials(&profile) {
+            Ok(()) => {
+                println!("Authenticated successfully");
+                std::process::exit(0)
+            }
+            Err(err) => {
+                eprintln_red!("Authentication failed: {}", err);
+                crate::print_cause_chain(&*err);
+                std::process::exit(1)
+            }
+        }
+    }
+}
+
+// The API and Git servers of the `--endpoint`, `--api-server`, and `--git-server` options,
+//   or None if they need to be prompted for
+fn servers_from_args(args: &Args) -> Option<(Url, Option<Url>)> {
+    let parse = |option: &str, input: &str| {
+        parse_url(input).unwrap_or_else(|err| quit_msg!("Invalid {} '{}': {}", option, input, err))
+    };
+
+    let (api_server, git_server) = match (&args.flag_endpoint, &args.flag_api_server) {
+        (Some(_), Some(_)) => quit_msg!("Use either --endpoint or --api-server, not both"),
+        (Some(endpoint), None) => endpoint_servers(parse("--endpoint", endpoint)),
+        // Without a Git server, it's derived from the API server when needed
+        (None, Some(api_server)) => (parse("--api-server", api_server), None),
+        (None, None) if args.flag_git_server.is_some() => {
+            quit_msg!("--git-server requires --endpoint or --api-server")
+        }
+        (None, None) => return None,
+    };
+    match args.flag_git_server {
+        Some(ref git_server) => Some((api_server, Some(parse("--git-server", git_server)))),
+        None => Some((api_server, git_server)),
+    }
+}
+
+// The API and Git servers of an endpoint, e.g. https://api.algorithmia.com and https://git.algorithmia.com
+fn endpoint_servers(mut endpoint: Url) -> (Url, Option<Url>) {
+    // Special handling of 'api.' as it's still likely that many enter the API endpoint
+    // instead of the parent domain.
+    remove_subdomain(&mut endpoint, "api");
+    (
+        prepend_subdomain(&endpoint, "api"),
+        Some(prepend_subdomain(&endpoint, "git")),
+    )
+}
+
+// Where the API key is kept, unless it's saved in the config file
+fn key_store(profile_name: &str, args: &Args) -> Option<ApiKeySource> {
+    let keyring = match args.flag_api_key_store.as_deref() {
+        None | Some("config") => false,
+        Some("keyring") => true,
+        Some(store) => quit_msg!(
+            "Invalid --api-key-store '{}'. Expected one of: config, keyring",
+            store
+        ),
+    };
+    match args.flag_api_key_cmd {
+        Some(_) if keyring => quit_msg!("Use either --api-key-store or --api-key-cmd, not both"),
+        Some(ref cmd) => Some(ApiKeySource::Command(cmd.clone())),
+        None if keyring => Some(ApiKeySource::Keyring(keyring_account(profile_name))),
+        None => None,
+    }
+}
+
+// The keyring account of a profile, which is reused when re-authenticating. New accounts are
+//   unique rather than the profile name, so that renaming a profile can't leave its key under a
+//   name that another profile later saves to (or removes).
+fn keyring_account(profile_name: &str) -> String {
+    match existing_key_source(profile_name) {
+        Some(ApiKeySource::Keyring(account)) => account,
+        _ => credential::new_keyring_account(),
+    }
+}
+
+fn existing_key_source(profile_name: &str) -> Option<ApiKeySource> {
+    Config::read_config()?
+        .get_profile(profile_name)?
+        .api_key_source()
+}
+
+// Makes a cheap authenticated request, so that a wrong API key or endpoint isn't saved silently
+fn check_credentials(profile: &Profile) -> Result<(), DynError> {
+    profile
+        .api_client()
+        .get("/v1/connector/data/.my")
+        .map(|_| ())
+}
+
+// Exits without changing the profile if the API key doesn't look right, or if it doesn't work
+//   with the server (unless it's saved anyway)
+fn validate(
+    profile_name: &str,
+    api_key: &str,
+    api_server: &Url,
+    git_server: &Option<Url>,
+    args: &Args,
+    interactive: bool,
+) {
+    if !is_valid_api_key(api_key) {
+        quit_msg!(
+            "That API Key doesn't look quite right. No changes made to '{}' profile.",
+            profile_name
+        );
+    }
+    if args.flag_no_check {
+        return;
+    }
+
+    let profile = Profile::new(
+        api_key.to_owned(),
+        Some(api_server.clone()),
+        git_server.clone(),
+    );
+    match check_credentials(&profile) {
+        Ok(()) => println!("Authenticated with {}", profile.api_server()),
+        Err(err) => {
+            eprintln_red!("Cannot authenticate with {}: {}", profile.api_server(), err);
+            crate::print_cause_chain(&*err);
+            if !interactive {
+                quit_msg!(
+                    "No changes made to '{}' profile. Use --no-check to save it anyway.",
                     profile_name
                 );
             }
-        } else {
-            println!(
-                "That API Key doesn't look quite right. No changes made to '{}' profile.",
-                profile_name
-            );
+            if !confirm("Save the profile anyway?") {
+                quit_msg!("No changes made to '{}' profile.", profile_name);
+            }
         }
     }
 }
 
+// Treats anything but 'y' or 'yes' (including EOF) as no
+fn confirm(question: &str) -> bool {
+    print!("{} [y/N] ", question);
+    let _ = io::stdout().flush();
+
+    let mut line = String::new();
+    match io::stdin().lock().read_line(&mut line) {
+        Ok(_) => {
+            let answer = line.trim().to_lowercase();
+            answer == "y" || answer == "yes"
+        }
+        Err(_) => false,
+    }
+}
+
+fn is_valid_api_key(api_key: &str) -> bool {
+    api_key.len() == 28 && api_key.starts_with("sim")
+}
+
+fn save_profile(
+    profile_name: &str,
+    api_key: String,
+    store: Option<ApiKeySource>,
+    api_server: Url,
+    git_server: Option<Url>,
+) {
+    let mut config = Config::read_config().unwrap_or_default();
+    let replaced = existing_key_source(profile_name);
+    let profile = match store {
+        Some(ApiKeySource::Keyring(account)) => {
+            credential::keyring_set(&account, &api_key).unwrap_or_else(|err| {
+                quit_msg!(
+                    "Cannot save the API Key in the OS keyring: {}. No changes made to '{}' profile.",
+                    err,
+                    profile_name
+                )
+            });
+            Profile::with_api_key_source(
+                ApiKeySource::Keyring(account),
+                Some(api_server),
+                git_server,
+            )
+        }
+        Some(source) => Profile::with_api_key_source(source, Some(api_server), git_server),
+        None => Profile::new(api_key, Some(api_server), git_server),
+    };
+
+    // A keyring entry that the profile no longer uses is removed
+    if let Some(ApiKeySource::Keyring(account)) = replaced {
+        if profile.api_key_source() != Some(ApiKeySource::Keyring(account.clone())) {
+            if let Err(err) = credential::keyring_delete(&account) {
+                eprintln!("Cannot remove the old API key from the OS keyring: {}", err);
+            }
+        }
+    }
+    config.update_profile(profile_name.into(), profile);
+    config.write_config();
+
+    if profile_name == config.default_profile() {
+        println!("Profile is ready to use. Try 'mia ls'");
+    } else {
+        println!(
+            "Profile is ready to use. Try 'mia ls --profile {}'",
+            profile_name
+        );
+    }
+}
+
 fn prompt_for_url() -> Option<Url> {
     let mut line = String::new();
     let stdin = io::stdin();
@@ -205,6 +484,47 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_servers_from_args() {
+        let args =
+            |endpoint: Option<&str>, api_server: Option<&str>, git_server: Option<&str>| Args {
+                flag_api_key_stdin: true,
+                flag_endpoint: endpoint.map(String::from),
+                flag_api_server: api_server.map(String::from),
+                flag_git_server: git_server.map(String::from),
+                flag_api_key_store: None,
+                flag_api_key_cmd: None,
+                flag_no_check: false,
+                flag_check: false,
+                cmd_whoami: false,
+            };
+        let url = |u| Url::parse(u).unwrap();
+
+        assert!(servers_from_args(&args(None, None, None)).is_none());
+        assert_eq!(
+            servers_from_args(&args(Some("api.example.com"), None, None)),
+            Some((
+                url("https://api.example.com"),
+                Some(url("https://git.example.com"))
+            ))
+        );
+        assert_eq!(
+            servers_from_args(&args(None, Some("https://ml.example.com"), None)),
+            Some((url("https://ml.example.com"), None))
+        );
+        assert_eq!(
+            servers_from_args(&args(
+                Some("example.com"),
+                None,
+                Some("https://code.example.com")
+            )),
+            Some((
+                url("https://api.example.com"),
+                Some(url("https://code.example.com"))
+            ))
+        );
+    }
+
     #[test]
     fn test_subdomain_prepend() {
         let assert_with_subdomain = |subdomain, input, expected| {
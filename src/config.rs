@@ -1,3 +1,5 @@
+use crate::api::ApiClient;
+use crate::credential::ApiKeySource;
 use algorithmia::Algorithmia;
 use std::collections::BTreeMap;
 use std::env;
@@ -15,9 +17,24 @@ use std::os::unix::fs::OpenOptionsExt;
 pub static DEFAULT_ENDPOINT: &str = "https://algorithmia.com";
 pub static DEFAULT_API_SERVER: &str = "https://api.algorithmia.com";
 pub static DEFAULT_GIT_SERVER: &str = "https://git.algorithmia.com";
+const DEFAULT_PROFILE: &str = "default";
 
-#[derive(Default, Deserialize, Serialize)]
+// Environment variables that take precedence over the values of the selected profile,
+//   or configure a profile when there is no config file
+pub static API_KEY_ENV: &str = "ALGORITHMIA_API_KEY";
+pub static API_SERVER_ENV: &str = "ALGORITHMIA_API";
+pub static GIT_SERVER_ENV: &str = "ALGORITHMIA_GIT";
+// Selects the profile when --profile isn't given, and the path of the config file
+pub static PROFILE_ENV: &str = "MIA_PROFILE";
+pub static CONFIG_ENV: &str = "MIA_CONFIG";
+
+#[derive(Clone, Default, Deserialize, Serialize)]
 pub struct Config {
+    // Used when neither --profile nor MIA_PROFILE is given (instead of 'default')
+    //   TOML requires values before tables, so this must precede `profiles`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default_profile: Option<String>,
+    #[serde(default)]
     profiles: BTreeMap<String, Profile>,
 }
 
@@ -25,7 +42,14 @@ pub struct Config {
 pub struct Profile {
     api_server: Option<String>,
     git_server: Option<String>,
+    // Empty when the API key is read from a command or the OS keyring instead
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     api_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    api_key_cmd: Option<String>,
+    // Account name of the API key in the OS keyring
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    api_key_keyring: Option<String>,
 }
 
 impl Profile {
@@ -34,6 +58,30 @@ impl Profile {
             api_server: api_server.map(|s| s.as_str().trim_end_matches('/').to_owned()),
             git_server: git_server.map(|s| s.as_str().trim_end_matches('/').to_owned()),
             api_key: api_key,
+            api_key_cmd: None,
+            api_key_keyring: None,
+        }
+    }
+
+    // A profile whose API key is read when it's looked up, instead of being stored in the config
+    pub fn with_api_key_source(
+        source: ApiKeySource,
+        api_server: Option<Url>,
+        git_server: Option<Url>,
+    ) -> Profile {
+        let mut profile = Profile::new(String::new(), api_server, git_server);
+        match source {
+            ApiKeySource::Command(cmd) => profile.api_key_cmd = Some(cmd),
+            ApiKeySource::Keyring(account) => profile.api_key_keyring = Some(account),
+        }
+        profile
+    }
+
+    pub fn api_key_source(&self) -> Option<ApiKeySource> {
+        match (&self.api_key_cmd, &self.api_key_keyring) {
+            (Some(cmd), _) => Some(ApiKeySource::Command(cmd.clone())),
+            (None, Some(account)) => Some(ApiKeySource::Keyring(account.clone())),
+            (None, None) => None,
         }
     }
 
@@ -44,6 +92,10 @@ impl Profile {
         }
     }
 
+    pub fn api_client(&self) -> ApiClient {
+        ApiClient::new(self.api_server(), &self.api_key)
+    }
+
     pub fn api_server(&self) -> &str {
         self.api_server
             .as_ref()
@@ -70,7 +122,8 @@ impl Profile {
 
 impl Config {
     pub fn read_config() -> Option<Config> {
-        let conf_path = get_config_path();
+        // Without a home directory (e.g. in some CI runners), there's no config unless MIA_CONFIG is set
+        let conf_path = config_path()?;
 
         match File::open(&conf_path) {
             Ok(mut f) => {
@@ -97,6 +150,48 @@ impl Config {
         self.profiles.get(name)
     }
 
+    pub fn profile_names(&self) -> Vec<&str> {
+        self.profiles.keys().map(String::as_str).collect()
+    }
+
+    pub fn remove_profile(&mut self, name: &str) -> Option<Profile> {
+        if self.default_profile.as_deref() == Some(name) {
+            self.default_profile = None;
+        }
+        self.profiles.remove(name)
+    }
+
+    pub fn rename_profile(&mut self, name: &str, new_name: &str) -> Result<(), String> {
+        if self.profiles.contains_key(new_name) {
+            return Err(format!("Profile '{}' already exists", new_name));
+        }
+        let profile = self
+            .profiles
+            .remove(name)
+            .ok_or_else(|| format!("Profile '{}' not found", name))?;
+        self.profiles.insert(new_name.to_owned(), profile);
+        if self.default_profile.as_deref() == Some(name) {
+            self.default_profile = Some(new_name.to_owned());
+        }
+        Ok(())
+    }
+
+    // The profile used when neither --profile nor MIA_PROFILE is given
+    pub fn default_profile(&self) -> &str {
+        self.default_profile.as_deref().unwrap_or(DEFAULT_PROFILE)
+    }
+
+    pub fn set_default_profile(&mut self, name: &str) -> Result<(), String> {
+        if !self.profiles.contains_key(name) {
+            return Err(format!("Profile '{}' not found", name));
+        }
+        self.default_profile = match name {
+            DEFAULT_PROFILE => None,
+            _ => Some(name.to_owned()),
+        };
+        Ok(())
+    }
+
     pub fn write_config(&mut self) {
         let output = toml::to_string(&self).unwrap();
 
@@ -108,26 +203,116 @@ impl Config {
 }
 
 impl Profile {
+    // Finds a profile in the config file, overridden by any ALGORITHMIA_* environment variables
+    //   Without a config file profile, ALGORITHMIA_API_KEY alone is enough to build one
     pub fn lookup(profile: &str) -> Profile {
-        Config::read_config()
-            .and_then(|c| c.get_profile(profile).cloned())
-            .unwrap_or_else(|| quit_msg!("{} profile not found. Run 'mia auth {0}'", profile))
+        Profile::find(Config::read_config(), profile, env_var)
+            .unwrap_or_else(|err| quit_msg!("{}", err))
+    }
+
+    fn find<F>(config: Option<Config>, profile: &str, var: F) -> Result<Profile, String>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let from_file = config.and_then(|c| c.get_profile(profile).cloned());
+        let mut found = Profile::with_overrides(from_file, var).ok_or_else(|| {
+            format!(
+                "{} profile not found. Run 'mia auth --profile {0}' or set {}",
+                profile, API_KEY_ENV
+            )
+        })?;
+        found
+            .read_api_key()
+            .map_err(|err| format!("Cannot read the API key of {} profile: {}", profile, err))?;
+        Ok(found)
+    }
+
+    // Reads the API key from its source, unless it's stored in the config or overridden
+    fn read_api_key(&mut self) -> Result<(), String> {
+        if self.api_key.is_empty() {
+            self.api_key = match self.api_key_source() {
+                Some(source) => source.read()?,
+                None => return Err("The profile has no API key".to_owned()),
+            };
+        }
+        Ok(())
+    }
+
+    fn with_overrides<F>(profile: Option<Profile>, var: F) -> Option<Profile>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let mut profile = match (profile, var(API_KEY_ENV)) {
+            (Some(p), _) => p,
+            (None, Some(api_key)) => Profile::new(api_key, None, None),
+            (None, None) => return None,
+        };
+        let server = |name| {
+            var(name).map(|value| match Url::parse(&value) {
+                Ok(url) => url.as_str().trim_end_matches('/').to_owned(),
+                Err(err) => quit_msg!("Invalid URL in {} '{}': {}", name, value, err),
+            })
+        };
+
+        if let Some(api_key) = var(API_KEY_ENV) {
+            profile.api_key = api_key;
+        }
+        if let Some(api_server) = server(API_SERVER_ENV) {
+            profile.api_server = Some(api_server);
+            // The profile's git server goes with its own API server, so derive one from this one
+            profile.git_server = None;
+        }
+        if let Some(git_server) = server(GIT_SERVER_ENV) {
+            profile.git_server = Some(git_server);
+        }
+        Some(profile)
     }
 }
 
-pub fn get_config_path() -> PathBuf {
+// Reads an environment variable, treating empty values as unset
+pub(crate) fn env_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.trim().is_empty())
+}
+
+// The profile used when --profile isn't given: MIA_PROFILE, then the config file's default
+pub fn default_profile_name() -> String {
+    env_var(PROFILE_ENV).unwrap_or_else(|| match Config::read_config() {
+        Some(config) => config.default_profile().to_owned(),
+        None => DEFAULT_PROFILE.to_owned(),
+    })
+}
+
+// The config file path, which is only None if neither MIA_CONFIG nor the home directory is set
+fn config_path() -> Option<PathBuf> {
+    if let Some(path) = env_var(CONFIG_ENV) {
+        return Some(PathBuf::from(path));
+    }
+
     let app_dir = if cfg!(windows) {
-        PathBuf::from(format!("{}/Algorithmia", env::var("LOCALAPPDATA").unwrap()))
+        PathBuf::from(format!("{}/Algorithmia", env::var("LOCALAPPDATA").ok()?))
     } else {
-        PathBuf::from(format!("{}/.algorithmia", env::var("HOME").unwrap()))
+        PathBuf::from(format!("{}/.algorithmia", env::var("HOME").ok()?))
     };
+    Some(app_dir.join("config"))
+}
+
+// The config file path, creating its directory if needed so that it can be written
+pub fn get_config_path() -> PathBuf {
+    let conf_path = config_path().unwrap_or_else(|| {
+        quit_msg!(
+            "Cannot find a home directory for the config file. Set {} to its path",
+            CONFIG_ENV
+        )
+    });
 
-    if !app_dir.is_dir() {
-        fs::create_dir(&app_dir).unwrap_or_else(|err| {
-            quit_err!("Failed to create app dir '{}': {}", app_dir.display(), err)
-        });
+    if let Some(app_dir) = conf_path.parent() {
+        if !app_dir.as_os_str().is_empty() && !app_dir.is_dir() {
+            fs::create_dir_all(app_dir).unwrap_or_else(|err| {
+                quit_err!("Failed to create app dir '{}': {}", app_dir.display(), err)
+            });
+        }
     }
-    app_dir.join("config")
+    conf_path
 }
 
 #[cfg(not(unix))]
@@ -146,3 +331,141 @@ fn open_writable_config() -> Result<File, io::Error> {
         .mode(0o600)
         .open(&conf_path)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // A fake environment for `Profile::with_overrides`
+    fn env(vars: Vec<(&str, &str)>) -> impl Fn(&str) -> Option<String> {
+        let vars: Vec<(String, String)> = vars
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |name| vars.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone())
+    }
+
+    #[test]
+    fn test_rename_default_profile() {
+        let mut config = Config::default();
+        let profile = Profile::new("simAAAAAAAAAAAAAAAAAAAAAAAAA".into(), None, None);
+        config.update_profile("default".into(), profile.clone());
+        config.update_profile("prod".into(), profile);
+        assert_eq!(config.default_profile(), "default");
+
+        config.set_default_profile("prod").unwrap();
+        assert!(config.set_default_profile("missing").is_err());
+        assert!(config.rename_profile("prod", "default").is_err());
+        config.rename_profile("prod", "production").unwrap();
+        assert_eq!(config.default_profile(), "production");
+        assert_eq!(config.profile_names(), vec!["default", "production"]);
+
+        // The default is written before the profiles tables, as TOML requires
+        let toml = toml::to_string(&config).unwrap();
+        assert!(toml.starts_with("default_profile = \"production\"\n"));
+
+        config.remove_profile("production");
+        assert_eq!(config.default_profile(), "default");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_api_key_cmd() {
+        let mut profile: Profile = toml::from_str("api_key_cmd = \"echo simCMD\"").unwrap();
+        assert_eq!(
+            profile.api_key_source(),
+            Some(ApiKeySource::Command("echo simCMD".into()))
+        );
+        profile.read_api_key().unwrap();
+        assert_eq!(profile.api_key(), "simCMD");
+
+        // The key isn't written back to the config
+        let profile =
+            Profile::with_api_key_source(ApiKeySource::Keyring("work".into()), None, None);
+        assert_eq!(
+            toml::to_string(&profile).unwrap(),
+            "api_key_keyring = \"work\"\n"
+        );
+
+        // ALGORITHMIA_API_KEY takes precedence, so the command isn't run
+        let mut profile = Profile::with_overrides(
+            Some(Profile::with_api_key_source(
+                ApiKeySource::Command("false".into()),
+                None,
+                None,
+            )),
+            env(vec![(API_KEY_ENV, "simENV")]),
+        )
+        .unwrap();
+        profile.read_api_key().unwrap();
+        assert_eq!(profile.api_key(), "simENV");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_lookup_keyring() {
+        crate::credential::fake_keyring();
+        let config: Config =
+            toml::from_str("[profiles.work]\napi_key_keyring = \"lookup-test\"\n").unwrap();
+        let lookup = |vars| Profile::find(Some(config.clone()), "work", env(vars));
+        assert!(lookup(vec![])
+            .err()
+            .unwrap()
+            .contains("Cannot read the API key"));
+
+        crate::credential::keyring_set("lookup-test", "simKEYRINGKEYRINGKEYRINGKEY").unwrap();
+        assert_eq!(
+            lookup(vec![]).unwrap().api_key(),
+            "simKEYRINGKEYRINGKEYRINGKEY"
+        );
+        let overridden = lookup(vec![(API_KEY_ENV, "simENV")]).unwrap();
+        assert_eq!(overridden.api_key(), "simENV");
+
+        crate::credential::keyring_delete("lookup-test").unwrap();
+        assert!(lookup(vec![]).is_err());
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let file_profile = Profile::new(
+            "simFILEFILEFILEFILEFILEFILEF".into(),
+            Some(Url::parse("https://api.example.com").unwrap()),
+            None,
+        );
+
+        // Without a config file, a profile needs at least an API key
+        assert!(Profile::with_overrides(None, env(vec![])).is_none());
+        let profile = Profile::with_overrides(None, env(vec![(API_KEY_ENV, "simENV")])).unwrap();
+        assert_eq!(profile.api_key(), "simENV");
+        assert_eq!(profile.api_server(), DEFAULT_API_SERVER);
+
+        // Environment variables take precedence over the file
+        let profile = Profile::with_overrides(Some(file_profile.clone()), env(vec![])).unwrap();
+        assert_eq!(profile.api_key(), "simFILEFILEFILEFILEFILEFILEF");
+        assert_eq!(profile.git_server(), "https://git.example.com");
+        let profile = Profile::with_overrides(
+            Some(file_profile),
+            env(vec![
+                (API_SERVER_ENV, "http://localhost:8080/"),
+                (GIT_SERVER_ENV, "http://localhost:8081"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(profile.api_key(), "simFILEFILEFILEFILEFILEFILEF");
+        assert_eq!(profile.api_server(), "http://localhost:8080");
+        assert_eq!(profile.git_server(), "http://localhost:8081");
+
+        // Without ALGORITHMIA_GIT, the git server follows ALGORITHMIA_API rather than the file
+        let file_profile = Profile::new(
+            "simFILEFILEFILEFILEFILEFILEF".into(),
+            Some(Url::parse("https://api.example.com").unwrap()),
+            Some(Url::parse("https://git.example.com").unwrap()),
+        );
+        let profile = Profile::with_overrides(
+            Some(file_profile),
+            env(vec![(API_SERVER_ENV, "https://api.test.example.com")]),
+        )
+        .unwrap();
+        assert_eq!(profile.git_server(), "https://git.test.example.com");
+    }
+}
@@ -0,0 +1,388 @@
+use std::fmt;
+#[cfg(unix)]
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Service name of API keys in the OS secret store, where each is saved under an account name
+static KEYRING_SERVICE: &str = "algorithmia";
+
+// Where a profile's API key is read from, when it isn't stored in the config file
+#[derive(Clone, Debug, PartialEq)]
+pub enum ApiKeySource {
+    // The output of a command, e.g. "pass show algorithmia"
+    Command(String),
+    // An entry of the OS secret store (Secret Service on Linux, Keychain on macOS)
+    Keyring(String),
+}
+
+impl ApiKeySource {
+    pub fn read(&self) -> Result<String, String> {
+        match *self {
+            ApiKeySource::Command(ref cmd) => read_command(cmd),
+            ApiKeySource::Keyring(ref account) => keyring_get(account),
+        }
+    }
+}
+
+impl fmt::Display for ApiKeySource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ApiKeySource::Command(ref cmd) => write!(f, "output of '{}'", cmd),
+            ApiKeySource::Keyring(ref account) => write!(f, "OS keyring, account '{}'", account),
+        }
+    }
+}
+
+// A new account name for a key in the OS keyring, e.g. "mia-17f3a2b4c5d6e7f8"
+//   Accounts don't depend on the profile name, since profiles can be renamed
+pub fn new_keyring_account() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    format!("mia-{:x}{:x}", nanos, std::process::id())
+}
+
+// Runs a command with the shell and reads the API key from the first line of its output
+//   stdin and stderr are inherited so that e.g. a GPG passphrase can be prompted for
+fn read_command(cmd: &str) -> Result<String, String> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let output = shell
+        .arg(cmd)
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|err| format!("Cannot run '{}': {}", cmd, err))?;
+    if !output.status.success() {
+        return Err(format!("'{}' failed with {}", cmd, output.status));
+    }
+    first_line(&output.stdout).ok_or_else(|| format!("'{}' printed no API key", cmd))
+}
+
+fn first_line(output: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(output)
+        .lines()
+        .next()
+        .map(|line| line.trim().to_owned())
+        .filter(|line| !line.is_empty())
+}
+
+// The OS secret store is accessed with its command line tool, so that no system libraries
+//   (e.g. libdbus) are needed to build or run mia without it. Both tools' arguments are built in
+//   tests, so that the fake tool can check them on any platform.
+#[cfg(any(target_os = "macos", all(unix, test)))]
+mod security {
+    use super::KEYRING_SERVICE;
+
+    // Only the platform's own tool is run outside of tests
+    #[cfg_attr(test, allow(dead_code))]
+    pub static TOOL: &str = "security";
+
+    pub fn get(account: &str) -> (Vec<String>, Option<String>) {
+        let args = &[
+            "find-generic-password",
+            "-s",
+            KEYRING_SERVICE,
+            "-a",
+            account,
+            "-w",
+        ];
+        (super::args(args), None)
+    }
+
+    // `security -i` reads commands from stdin, so that the key isn't visible to other users in
+    //   the process list. (With -w and no value, `security` would prompt on the terminal instead.)
+    pub fn set(account: &str, api_key: &str) -> (Vec<String>, Option<String>) {
+        let command = format!(
+            "add-generic-password -U -s {} -a {} -w {}\n",
+            quote(KEYRING_SERVICE),
+            quote(account),
+            quote(api_key)
+        );
+        (super::args(&["-i"]), Some(command))
+    }
+
+    pub fn delete(account: &str) -> (Vec<String>, Option<String>) {
+        let args = &[
+            "delete-generic-password",
+            "-s",
+            KEYRING_SERVICE,
+            "-a",
+            account,
+        ];
+        (super::args(args), None)
+    }
+
+    // Quotes an argument of an interactive command, which is split on whitespace
+    fn quote(arg: &str) -> String {
+        format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+#[cfg(any(all(unix, not(target_os = "macos")), all(unix, test)))]
+mod secret_tool {
+    use super::KEYRING_SERVICE;
+
+    // Only the platform's own tool is run outside of tests
+    #[cfg_attr(test, allow(dead_code))]
+    pub static TOOL: &str = "secret-tool";
+
+    pub fn get(account: &str) -> (Vec<String>, Option<String>) {
+        let args = &["lookup", "service", KEYRING_SERVICE, "account", account];
+        (super::args(args), None)
+    }
+
+    pub fn set(account: &str, api_key: &str) -> (Vec<String>, Option<String>) {
+        let label = format!("Algorithmia API key ({})", account);
+        let args = &[
+            "store",
+            "--label",
+            &label,
+            "service",
+            KEYRING_SERVICE,
+            "account",
+            account,
+        ];
+        (super::args(args), Some(api_key.to_owned()))
+    }
+
+    pub fn delete(account: &str) -> (Vec<String>, Option<String>) {
+        let args = &["clear", "service", KEYRING_SERVICE, "account", account];
+        (super::args(args), None)
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+use self::secret_tool as keyring;
+#[cfg(target_os = "macos")]
+use self::security as keyring;
+
+// The secret store's tool, which tests replace with a fake (see `fake_keyring`)
+#[cfg(all(unix, not(test)))]
+fn keyring_tool() -> String {
+    keyring::TOOL.to_owned()
+}
+
+// Set by tests to replace the secret store's tool with a fake (see `fake_keyring`)
+#[cfg(all(unix, test))]
+static FAKE_KEYRING_ENV: &str = "MIA_TEST_KEYRING_TOOL";
+
+#[cfg(all(unix, test))]
+fn keyring_tool() -> String {
+    std::env::var(FAKE_KEYRING_ENV).unwrap_or_else(|_| keyring::TOOL.to_owned())
+}
+
+// Replaces the secret store's tool with a script that understands both `secret-tool` and
+//   `security` arguments, and stores each key in a file named after its account. Tests share
+//   the one fake (so they must use different accounts), since the environment is per process.
+//   Like `security`, it only takes a key from stdin as a `security -i` command.
+#[cfg(all(unix, test))]
+pub(crate) fn fake_keyring() {
+    use std::os::unix::fs::PermissionsExt;
+    use std::sync::Once;
+
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        let dir = std::env::temp_dir().join(format!("mia-keyring-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("keyring");
+        std::fs::write(
+            &script,
+            format!(
+                r#"#!/bin/sh
+if [ "$1" = -i ]; then
+  read -r line || exit 2
+  eval "set -- $line"
+  interactive=1
+fi
+action=$1
+while [ $# -gt 0 ]; do
+  case $1 in
+    account|-a) account=$2 ;;
+    -w) key=$2 ;;
+  esac
+  shift
+done
+entry="{}/$account"
+case $action in
+  store) head -n 1 > "$entry" ;;
+  add-generic-password)
+    if [ -z "$interactive" ] || [ -z "$key" ]; then
+      echo "the key must be given to add-generic-password -w in security -i" >&2
+      exit 2
+    fi
+    printf '%s\n' "$key" > "$entry" ;;
+  lookup|find-generic-password) cat "$entry" 2>/dev/null || exit 44 ;;
+  clear|delete-generic-password) rm "$entry" 2>/dev/null || exit 44 ;;
+  *) exit 2 ;;
+esac
+"#,
+                dir.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::env::set_var(FAKE_KEYRING_ENV, &script);
+    });
+}
+
+#[cfg(unix)]
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|s| s.to_string()).collect()
+}
+
+// Runs the secret store's tool with arguments and input from one of the `keyring` functions
+#[cfg(unix)]
+fn keyring_run((args, input): (Vec<String>, Option<String>)) -> Result<Vec<u8>, String> {
+    let mut child = Command::new(keyring_tool())
+        .args(&args)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => format!("The OS keyring requires '{}'", keyring::TOOL),
+            _ => format!("Cannot run '{}': {}", keyring::TOOL, err),
+        })?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin
+            .write_all(input.as_bytes())
+            .map_err(|err| format!("Cannot write to '{}': {}", keyring::TOOL, err))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|err| format!("Cannot run '{}': {}", keyring::TOOL, err))?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    match (output.status.success(), stderr.trim()) {
+        (true, _) => Ok(output.stdout),
+        // e.g. `secret-tool lookup` prints nothing when there's no such entry
+        (false, "") => Err(format!("'{}' failed with {}", keyring::TOOL, output.status)),
+        (false, msg) => Err(format!(
+            "'{}' failed with {}: {}",
+            keyring::TOOL,
+            output.status,
+            msg
+        )),
+    }
+}
+
+#[cfg(unix)]
+fn keyring_get(account: &str) -> Result<String, String> {
+    let output = keyring_run(keyring::get(account))?;
+    first_line(&output).ok_or_else(|| format!("No API key in the OS keyring for '{}'", account))
+}
+
+// The key is read back, since `security -i` may exit successfully when its command failed
+#[cfg(unix)]
+pub fn keyring_set(account: &str, api_key: &str) -> Result<(), String> {
+    keyring_run(keyring::set(account, api_key))?;
+    match keyring_get(account) {
+        Ok(ref stored) if stored == api_key => Ok(()),
+        _ => Err(format!("'{}' didn't store the API key", keyring::TOOL)),
+    }
+}
+
+#[cfg(unix)]
+pub fn keyring_delete(account: &str) -> Result<(), String> {
+    keyring_run(keyring::delete(account)).map(|_| ())
+}
+
+// Windows has no command line tool that can read a stored credential
+#[cfg(not(unix))]
+static KEYRING_UNSUPPORTED: &str =
+    "The OS keyring isn't supported on this platform. Use api_key_cmd instead";
+
+#[cfg(not(unix))]
+fn keyring_get(_account: &str) -> Result<String, String> {
+    Err(KEYRING_UNSUPPORTED.to_owned())
+}
+
+#[cfg(not(unix))]
+pub fn keyring_set(_account: &str, _api_key: &str) -> Result<(), String> {
+    Err(KEYRING_UNSUPPORTED.to_owned())
+}
+
+#[cfg(not(unix))]
+pub fn keyring_delete(_account: &str) -> Result<(), String> {
+    Err(KEYRING_UNSUPPORTED.to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_keyring() {
+        fake_keyring();
+        let source = ApiKeySource::Keyring("work".into());
+        assert!(source.read().is_err());
+
+        keyring_set("work", "simKEYRINGKEYRINGKEYRINGKEY").unwrap();
+        assert_eq!(source.read(), Ok("simKEYRINGKEYRINGKEYRINGKEY".to_owned()));
+        keyring_set("work", "simREPLACEDREPLACEDREPLACEDR").unwrap();
+        assert_eq!(source.read(), Ok("simREPLACEDREPLACEDREPLACEDR".to_owned()));
+
+        keyring_delete("work").unwrap();
+        assert!(source.read().is_err());
+        assert!(keyring_delete("work").is_err());
+    }
+
+    #[cfg(unix)]
+    type ToolArgs = (Vec<String>, Option<String>);
+
+    // Stores, reads, and deletes a key with one tool's arguments, using the fake tool
+    #[cfg(unix)]
+    fn check_tool(
+        set: fn(&str, &str) -> ToolArgs,
+        get: fn(&str) -> ToolArgs,
+        delete: fn(&str) -> ToolArgs,
+    ) {
+        let (account, key) = ("tools", "simTOOLSTOOLSTOOLSTOOLSTOOL");
+        let (args, input) = set(account, key);
+        assert!(args.iter().all(|arg| !arg.contains(key)));
+        assert!(input.unwrap().contains(key));
+
+        keyring_run(set(account, key)).unwrap();
+        let stored = keyring_run(get(account)).unwrap();
+        assert_eq!(first_line(&stored), Some(key.to_owned()));
+        keyring_run(delete(account)).unwrap();
+        assert!(keyring_run(get(account)).is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_keyring_tools() {
+        fake_keyring();
+        check_tool(secret_tool::set, secret_tool::get, secret_tool::delete);
+        check_tool(security::set, security::get, security::delete);
+
+        // `security` would prompt on the terminal for a key that isn't sent with `security -i`
+        let args = args(&["add-generic-password", "-a", "tools", "-w"]);
+        assert!(keyring_run((args, Some("simTOOLS\n".to_owned()))).is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_read_command() {
+        let source = ApiKeySource::Command("printf ' simAAA \\nsecond line'".into());
+        assert_eq!(source.read(), Ok("simAAA".to_owned()));
+        assert!(ApiKeySource::Command("true".into()).read().is_err());
+        assert!(ApiKeySource::Command("echo simAAA; exit 3".into())
+            .read()
+            .is_err());
+    }
+}
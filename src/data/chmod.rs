@@ -0,0 +1,82 @@
+use crate::api::ApiClient;
+use crate::config::Profile;
+use crate::data::{parse_read_acl, set_dir_acl, ACL_NAMES};
+use crate::output;
+use crate::CmdRunner;
+use algorithmia::data::HasDataPath;
+use algorithmia::Algorithmia;
+use docopt::Docopt;
+use rustc_serialize::json::ToJson;
+use std::vec::IntoIter;
+
+static USAGE: &str = r##"Usage:
+  mia chmod <acl> <data-dir>
+
+  Change the read permissions of an Algorithmia data directory
+
+  <acl>         One of:
+                  private     Readable only by you
+                  my-algos    Readable by your algorithms (regardless of caller)
+                  public      Readable by any user
+  <data-dir>    Specifies the Algorithmia Data URI
+                The 'data://' prefix is optional
+
+  Examples:
+    mia chmod public .my/shared                          Make a directory public
+    mia chmod private data://.my/secrets                 Make a directory private
+"##;
+
+#[derive(RustcDecodable, Debug)]
+struct Args {
+    arg_acl: String,
+    arg_data_dir: String,
+}
+
+pub struct ChMod {
+    client: Algorithmia,
+    api: ApiClient,
+}
+impl CmdRunner for ChMod {
+    fn get_usage() -> &'static str {
+        USAGE
+    }
+
+    fn cmd_main(&self, argv: IntoIter<String>) {
+        let args: Args = Docopt::new(USAGE)
+            .and_then(|d| d.argv(argv).decode())
+            .unwrap_or_else(|e| e.exit());
+
+        self.chmod(&args.arg_acl, &args.arg_data_dir);
+    }
+}
+
+impl ChMod {
+    pub fn new(profile: Profile) -> Self {
+        ChMod {
+            client: profile.client(),
+            api: profile.api_client(),
+        }
+    }
+
+    fn chmod(&self, acl_name: &str, path: &str) {
+        let acl = parse_read_acl(acl_name).unwrap_or_else(|| {
+            quit_msg!("Invalid ACL '{}'. Expected one of: {}", acl_name, ACL_NAMES)
+        });
+
+        let my_dir = self.client.dir(path);
+        match set_dir_acl(&self.api, &my_dir, acl) {
+            Ok(_) => {
+                let data_uri = my_dir.to_data_uri();
+                output::report(
+                    &format!("Changed {} to {}", data_uri, acl_name),
+                    output::object(vec![
+                        ("data_uri", data_uri.to_json()),
+                        ("acl", acl_name.to_json()),
+                        ("status", "ok".to_json()),
+                    ]),
+                );
+            }
+            Err(err) => quit_msg!("Error changing permissions: {}", err),
+        };
+    }
+}
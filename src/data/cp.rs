@@ -1,13 +1,24 @@
-use super::size_with_suffix;
+use super::progress::{Progress, ProgressReader, Transfer};
+use super::verify::{self, Manifest, Verified};
+use super::{is_remote, size_with_suffix};
+use crate::api::ApiClient;
 use crate::config::Profile;
+use crate::output::{self, Records};
 use crate::CmdRunner;
-use algorithmia::data::{DataFile, DataItem, HasDataPath};
-use algorithmia::Algorithmia;
-use chan;
+use algorithmia::data::{DataAcl, DataDir, DataFile, DataItem, HasDataPath};
+use algorithmia::{Algorithmia, Body};
+use chan::{self, Sender};
 use docopt::Docopt;
-use std::fs::File;
-use std::path::Path;
+use reqwest::blocking::Response;
+use reqwest::header::CONTENT_RANGE;
+use reqwest::StatusCode;
+use rustc_serialize::json::ToJson;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use std::vec::IntoIter;
 use std::{clone, cmp, fs, io, thread};
 
@@ -17,28 +28,49 @@ static USAGE: &'static str = r##"Usage:
 
   Copy files to or from the Algorithmia Data API
 
+  Progress of each file, throughput, and the estimated time remaining are shown on stderr,
+  or summarized periodically when stderr is not a terminal.
+
+  Failed downloads are retried with increasing delays, resuming partially downloaded files
+  where possible. Files that still fail are listed once all other files have been copied.
+
   An Algorithmia Data URL must be prefixed with data:// in order to avoid potential path ambiguity
 
   Options:
-    -c <CONCURRENCY>    Number of threads for uploading in parallel [Default: 8]
+    -c <CONCURRENCY>        Number of threads for uploading in parallel [Default: 8]
+    -r, --recursive         Recursive copy if the source is a directory
+    --retries <RETRIES>     Number of times to retry a failed download [Default: 5]
+    --verify                Check that each copy has the same size as its source, and the same
+                              MD5 checksum if the Data API provides one (copies are reported as
+                              not verified if it provides neither)
+    --manifest <file>       Write MD5 checksums of the local files to <file> in the format of
+                              'md5sum' (implies --verify)
 
   Examples:
     mia cp file1.jpg file2.jpg data://.my/foo          Upload 2 files to your 'foo' data directory
     mia cp data://.my/foo/file1.jpg .                  Download file1.jpg to the workig directory
+    mia cp -r images data://.my/foo                    Upload the 'images' directory tree into 'foo'
+    mia cp -r data://.my/foo/images .                  Download the 'images' directory tree
+    mia cp data://.my/foo/file1.jpg s3://bucket/foo    Copy between data sources without downloading
+    mia cp -r --manifest models.md5 data://.my/models .
+                                                       Download and verify models, then check them
+                                                         later with 'md5sum -c models.md5'
 "##;
 
-// TODO:
-// -r                   Recursive copy if the source is a directory
-
 #[derive(RustcDecodable, Debug)]
 struct Args {
     arg_source: Vec<String>,
     arg_dest: String,
     flag_c: u32,
+    flag_recursive: bool,
+    flag_retries: u32,
+    flag_verify: bool,
+    flag_manifest: Option<String>,
 }
 
 pub struct Cp {
     client: Algorithmia,
+    api: ApiClient,
 }
 impl CmdRunner for Cp {
     fn get_usage() -> &'static str {
@@ -50,15 +82,19 @@ impl CmdRunner for Cp {
             .and_then(|d| d.argv(argv).decode())
             .unwrap_or_else(|e| e.exit());
 
-        let cp_client = CpClient::new(self.client.clone(), args.flag_c, &args.arg_dest);
+        let cp_client = CpClient::new(self.client.clone(), self.api.clone(), &args);
 
-        // Download if the dest is a local path or prefixed with file://_
-        //   otherwise, assume upload
-        let dest_parts: Vec<_> = args.arg_dest.splitn(2, "://").collect();
-        if dest_parts.len() < 2 || dest_parts[0] == "file" {
+        // Download if the dest is a local path or prefixed with file://
+        //   copy between remote paths if both source and dest are remote, otherwise assume upload
+        if !is_remote(&args.arg_dest) {
             cp_client.download(args.arg_source);
         } else {
-            cp_client.upload(args.arg_source);
+            let remote_count = args.arg_source.iter().filter(|s| is_remote(s)).count();
+            match remote_count {
+                0 => cp_client.upload(args.arg_source),
+                n if n == args.arg_source.len() => cp_client.copy_remote(args.arg_source),
+                _ => quit_msg!("Cannot copy local and remote sources at the same time"),
+            }
         }
     }
 }
@@ -67,49 +103,279 @@ impl Cp {
     pub fn new(profile: Profile) -> Self {
         Cp {
             client: profile.client(),
+            api: profile.api_client(),
         }
     }
 }
 
-struct CpClient {
+pub(super) struct CpClient {
     client: Algorithmia,
+    api: ApiClient,
     max_concurrency: u32,
+    retries: u32,
+    recursive: bool,
+    verify: bool,
+    manifest: Option<Arc<Manifest>>,
     dest: Arc<String>,
+    records: Arc<Records>,
+    progress: Arc<Progress>,
+    // (source, error) of each file that couldn't be copied
+    failures: Arc<Mutex<Vec<(String, String)>>>,
 }
 
 impl clone::Clone for CpClient {
     fn clone(&self) -> CpClient {
         CpClient {
             client: self.client.clone(),
+            api: self.api.clone(),
             max_concurrency: self.max_concurrency,
+            retries: self.retries,
+            recursive: self.recursive,
+            verify: self.verify,
+            manifest: self.manifest.clone(),
             dest: self.dest.clone(),
+            records: self.records.clone(),
+            progress: self.progress.clone(),
+            failures: self.failures.clone(),
         }
     }
 }
 
+// Without any workers, queuing the first file would block forever
+fn check_concurrency(max_concurrency: u32) {
+    if max_concurrency < 1 {
+        quit_msg!("-c must be at least 1");
+    }
+}
+
+// A single file to copy: (source path, destination path, size if already known)
+//   the destination may be a directory, in which case the source basename is kept
+type CopyJob = (String, String, Option<u64>);
+
 impl CpClient {
-    fn new(client: Algorithmia, max_concurrency: u32, dest: &str) -> CpClient {
+    fn new(client: Algorithmia, api: ApiClient, args: &Args) -> CpClient {
+        check_concurrency(args.flag_c);
+        CpClient {
+            client,
+            api,
+            max_concurrency: args.flag_c,
+            retries: args.flag_retries,
+            recursive: args.flag_recursive,
+            verify: args.flag_verify || args.flag_manifest.is_some(),
+            manifest: args
+                .flag_manifest
+                .as_ref()
+                .map(|path| Arc::new(Manifest::new(path))),
+            dest: Arc::new(args.arg_dest.to_string()),
+            records: Arc::new(Records::new()),
+            progress: Progress::new(),
+            failures: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    // A client for transfers planned by another command (i.e. `mia sync`), which reports their
+    //   results itself but shares the progress display, download retries, and failure report
+    //   of `mia cp`
+    pub(super) fn for_transfers(
+        client: Algorithmia,
+        api: ApiClient,
+        max_concurrency: u32,
+        retries: u32,
+    ) -> CpClient {
+        check_concurrency(max_concurrency);
         CpClient {
-            client: client,
-            max_concurrency: max_concurrency,
-            dest: Arc::new(dest.to_string()),
+            client,
+            api,
+            max_concurrency,
+            retries,
+            recursive: false,
+            verify: false,
+            manifest: None,
+            dest: Arc::new(String::new()),
+            records: Arc::new(Records::new()),
+            progress: Progress::new(),
+            failures: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub(super) fn progress(&self) -> &Progress {
+        &self.progress
+    }
+
+    pub(super) fn max_concurrency(&self) -> u32 {
+        self.max_concurrency
+    }
+
+    pub(super) fn records(&self) -> &Records {
+        &self.records
+    }
+
+    // Uploads a local file, showing its progress, and returns the bytes uploaded
+    //   `size` is the size already counted when the file was queued, if any
+    pub(super) fn upload_file(
+        &self,
+        path: &str,
+        dest: &DataFile,
+        size: Option<u64>,
+    ) -> Result<u64, String> {
+        let file = File::open(path).map_err(|err| err.to_string())?;
+        let bytes = file.metadata().map(|m| m.len()).unwrap_or(0);
+        let mut transfer = Transfer::start(&self.progress, path, size);
+        transfer.set_size(bytes);
+        let reader = ProgressReader::new(file, transfer);
+        let body = match bytes {
+            0 => Body::new(reader),
+            size => Body::sized(reader, size),
+        };
+        dest.put(body).map(|_| bytes).map_err(|e| e.to_string())
+    }
+
+    // Downloads a file with retries, showing its progress, and returns the bytes downloaded
+    //   `size` is the size already counted when the file was queued, if any
+    pub(super) fn download_file(
+        &self,
+        source: &DataFile,
+        local_path: &str,
+        size: Option<u64>,
+    ) -> Result<u64, String> {
+        let transfer = Transfer::start(&self.progress, &source.to_data_uri(), size);
+        self.download_with_retry(source, local_path, transfer)
+    }
+
+    // Prints the result of copying a file, or records it for structured output
+    fn report_copy(
+        &self,
+        text: String,
+        source: &str,
+        dest: &str,
+        bytes: u64,
+        verified: Option<Verified>,
+    ) {
+        let mut record = output::object(vec![
+            ("source", source.to_json()),
+            ("dest", dest.to_json()),
+            ("bytes", bytes.to_json()),
+            ("status", "ok".to_json()),
+        ]);
+        let text = match verified {
+            Some(Verified::Unverified) => {
+                output::insert(&mut record, "verified", "none".to_json());
+                format!("{}, not verified (no size or MD5 from the API)", text)
+            }
+            Some(verified) => {
+                output::insert(&mut record, "verified", verified.to_string().to_json());
+                format!("{}, verified {}", text, verified)
+            }
+            None => text,
+        };
+        self.progress.complete();
+        self.progress.suspend(|| self.records.report(&text, record));
+    }
+
+    fn report_finished(&self, action: &str, count: u32) {
+        self.progress.finish();
+        if output::is_text() {
+            println!(
+                "Finished {} {} file(s), {}",
+                action,
+                count,
+                self.progress.summary()
+            );
+        } else {
+            self.records.finish();
+        }
+    }
+
+    // Prints an error for a file without stopping the copy of other files
+    pub(super) fn report_failure(&self, action: &str, source: &str, err: String) {
+        self.progress
+            .suspend(|| eprintln_red!("Failed to {} {}: {}", action, source, err));
+        self.records.add(output::object(vec![
+            ("source", source.to_json()),
+            ("status", "error".to_json()),
+            ("error", err.to_json()),
+        ]));
+        self.failures
+            .lock()
+            .unwrap()
+            .push((source.to_string(), err));
+    }
+
+    // Lists every file that failed, exiting with an error if there were any
+    pub(super) fn exit_if_failed(&self, action: &str) {
+        let failures = self.failures.lock().unwrap();
+        if failures.is_empty() {
+            return;
+        }
+        eprintln_red!("Failed to {} {} file(s):", action, failures.len());
+        for (source, err) in failures.iter() {
+            eprintln!("  {}: {}", source, err);
+        }
+        ::std::process::exit(1);
+    }
+
+    // Verifies a copy between a local and a remote file if requested
+    fn check_local(&self, remote: &DataFile, local: &Path) -> Result<Option<Verified>, String> {
+        if !self.verify {
+            return Ok(None);
+        }
+        verify::verify_local(&self.api, remote, local, self.manifest.as_deref()).map(Some)
+    }
+
+    fn check_remote(&self, source: &DataFile, dest: &DataFile) -> Result<Option<Verified>, String> {
+        if !self.verify {
+            return Ok(None);
+        }
+        verify::verify_remote(&self.api, source, dest).map(Some)
+    }
+
+    fn write_manifest(&self) {
+        if let Some(ref manifest) = self.manifest {
+            match manifest.write() {
+                Ok(()) if output::is_text() => {
+                    println!("Wrote MD5 checksums to {}", manifest.path())
+                }
+                Ok(()) => (),
+                Err(err) => quit_err!("Error writing {}: {}", manifest.path(), err),
+            }
+        }
+    }
+
+    // Queues a file to copy, counting it towards the progress total
+    fn send(&self, tx: &Sender<CopyJob>, source: String, dest: String, size: Option<u64>) {
+        self.progress.queue(size);
+        tx.send((source, dest, size));
+    }
+
+    // Recursion can queue up any number of files, so only limit threads by source count when not recursing
+    fn concurrency(&self, source_count: usize) -> usize {
+        if self.recursive {
+            self.max_concurrency as usize
+        } else {
+            cmp::min(source_count, self.max_concurrency as usize)
         }
     }
 
     fn upload(&self, sources: Vec<String>) {
-        // As long as we aren't recursing, we can be more aggressive in limiting threads we spin up
-        // TODO: when supporting dir recursion, fall-back to max_concurrency
-        let concurrency = cmp::min(sources.len(), self.max_concurrency as usize);
+        let concurrency = self.concurrency(sources.len());
 
         let (tx, rx) = chan::sync(self.max_concurrency as usize);
         let wg = chan::WaitGroup::new();
         let completed = Arc::new(Mutex::new(0));
 
         // One Producer thread queuing up file paths to upload
+        let producer_conn = self.clone();
         thread::spawn(move || {
             for path in sources {
-                // TODO: if recursing and is_dir: recurse_and_send(&tx, path)
-                tx.send(path);
+                if !Path::new(&path).is_dir() {
+                    let size = fs::metadata(&path).map(|m| m.len()).ok();
+                    producer_conn.send(&tx, path, producer_conn.dest.to_string(), size);
+                } else if producer_conn.recursive {
+                    let remote_dir = producer_conn.remote_root(&path);
+                    producer_conn.recurse_and_send(&tx, Path::new(&path), &remote_dir);
+                } else {
+                    eprintln!("Skipping directory {} (use -r to copy recursively)", path);
+                }
             }
             drop(tx);
         });
@@ -124,33 +390,26 @@ impl CpClient {
             let thread_completed = completed.clone();
 
             thread::spawn(move || {
-                for rx_path in thread_rx {
-                    let dest_obj = thread_conn.client.data(&*thread_conn.dest);
-                    let put_res = match dest_obj.into_type() {
-                        // If dest exists as DataFile, overwrite it
-                        Ok(DataItem::File(f)) => {
-                            let file = File::open(&*rx_path).unwrap();
-                            f.put(file).map(|_| f.to_data_uri())
-                        }
-                        // If dest exists as DataDir, add file to dir
-                        Ok(DataItem::Dir(d)) => d
-                            .put_file(&rx_path)
-                            .map(|_| d.child::<DataFile>(&rx_path).to_data_uri()),
-                        // Otherwise, try adding new file with exact path as dest
-                        Err(_) => {
-                            let file = File::open(&*rx_path).unwrap();
-                            let f = thread_conn.client.file(&*thread_conn.dest);
-                            f.put(file).map(|_| f.to_data_uri())
-                        }
-                    };
-
-                    match put_res {
-                        Ok(uri) => {
-                            println!("Uploaded {}", uri);
-                            let mut count = thread_completed.lock().unwrap();
-                            *count += 1;
-                        }
-                        Err(e) => quit_err!("Error uploading {}: {}", rx_path, e),
+                for (rx_path, rx_dest, rx_size) in thread_rx {
+                    let filename = Path::new(&rx_path).file_name().unwrap().to_string_lossy();
+                    let f = remote_dest_file(&thread_conn.client, &rx_dest, &filename);
+                    match thread_conn.upload_file(&rx_path, &f, rx_size) {
+                        Ok(bytes) => match thread_conn.check_local(&f, Path::new(&rx_path)) {
+                            Ok(verified) => {
+                                let uri = f.to_data_uri();
+                                thread_conn.report_copy(
+                                    format!("Uploaded {}", uri),
+                                    &rx_path,
+                                    &uri,
+                                    bytes,
+                                    verified,
+                                );
+                                let mut count = thread_completed.lock().unwrap();
+                                *count += 1;
+                            }
+                            Err(err) => thread_conn.report_failure("upload", &rx_path, err),
+                        },
+                        Err(err) => thread_conn.report_failure("upload", &rx_path, err),
                     };
                 }
                 thread_wg.done();
@@ -158,23 +417,84 @@ impl CpClient {
         }
 
         wg.wait();
-        println!("Finished uploading {} file(s)", *completed.lock().unwrap());
+        self.write_manifest();
+        self.report_finished("uploading", *completed.lock().unwrap());
+        self.exit_if_failed("upload");
+    }
+
+    // Determines the remote directory that a local directory is copied into
+    fn remote_root(&self, local_dir: &str) -> DataDir {
+        let dirname = match Path::new(local_dir).canonicalize() {
+            Ok(ref p) => p.file_name().map(|f| f.to_string_lossy().into_owned()),
+            Err(err) => quit_err!("Error reading {}: {}", local_dir, err),
+        };
+        let root = remote_dest_dir(&self.client, &self.dest, local_dir, dirname);
+        create_remote_dir(&root);
+        root
+    }
+
+    // A directory that can't be read is reported as a failure, and the rest are still copied
+    //   Symlinked directories are skipped, since following them could recurse forever
+    fn recurse_and_send(&self, tx: &Sender<CopyJob>, local_dir: &Path, remote_dir: &DataDir) {
+        let dir_failed = |err: io::Error| {
+            let source = local_dir.to_string_lossy();
+            let err = format!("Error reading directory: {}", err);
+            self.report_failure("upload", &source, err)
+        };
+        let entries = match fs::read_dir(local_dir) {
+            Ok(entries) => entries,
+            Err(err) => return dir_failed(err),
+        };
+        for entry in entries {
+            // Unlike `Path::is_dir`, the entry's own type doesn't follow symlinks
+            let (path, file_type) = match entry.and_then(|e| e.file_type().map(|t| (e.path(), t))) {
+                Ok(entry) => entry,
+                Err(err) => return dir_failed(err),
+            };
+            if file_type.is_symlink() && path.is_dir() {
+                self.progress.suspend(|| {
+                    eprintln!("Skipping symlinked directory {}", path.display());
+                });
+            } else if file_type.is_dir() {
+                let filename = path.file_name().unwrap().to_string_lossy();
+                let child_dir: DataDir = remote_dir.child(&filename);
+                create_remote_dir(&child_dir);
+                self.recurse_and_send(tx, &path, &child_dir);
+            } else {
+                let size = fs::metadata(&path).map(|m| m.len()).ok();
+                self.send(
+                    tx,
+                    path.to_string_lossy().into_owned(),
+                    remote_dir.to_data_uri(),
+                    size,
+                );
+            }
+        }
     }
 
     fn download(&self, sources: Vec<String>) {
-        // As long as we aren't recursing, we can be more aggressive in limiting threads we spin up
-        // TODO: when supporting datadir recursion, fall-back to max_concurrency
-        let concurrency = cmp::min(sources.len(), self.max_concurrency as usize);
+        let concurrency = self.concurrency(sources.len());
 
         let (tx, rx) = chan::sync(self.max_concurrency as usize);
         let wg = chan::WaitGroup::new();
         let completed = Arc::new(Mutex::new(0));
 
         // One Producer thread queuing up file paths to upload
+        let producer_conn = self.clone();
         thread::spawn(move || {
             for path in sources {
-                // TODO: if recursing and is_dir: recurse_remote_and_send(&tx, path)
-                tx.send(path);
+                if !producer_conn.recursive {
+                    producer_conn.send(&tx, path, producer_conn.dest.to_string(), None);
+                    continue;
+                }
+                match producer_conn.client.data(&path).into_type() {
+                    Ok(DataItem::Dir(d)) => {
+                        let local_dir = producer_conn.local_root(&d);
+                        producer_conn.recurse_remote_and_send(&tx, &d, &local_dir);
+                    }
+                    // Let the download report any errors for files or missing paths
+                    _ => producer_conn.send(&tx, path, producer_conn.dest.to_string(), None),
+                }
             }
             drop(tx);
         });
@@ -189,15 +509,31 @@ impl CpClient {
             let thread_completed = completed.clone();
 
             thread::spawn(move || {
-                for rx_path in thread_rx {
+                for (rx_path, rx_dest, rx_size) in thread_rx {
                     let my_file = thread_conn.client.file(&*rx_path);
-                    match download_file(&my_file, &*thread_conn.dest) {
+                    match thread_conn.download_file(&my_file, &rx_dest, rx_size) {
                         Ok(bytes) => {
-                            println!("Downloaded {} ({}B)", rx_path, size_with_suffix(bytes));
-                            let mut count = thread_completed.lock().unwrap();
-                            *count += 1;
+                            let local_path = local_dest_path(&my_file, &rx_dest);
+                            match thread_conn.check_local(&my_file, &local_path) {
+                                Ok(verified) => {
+                                    thread_conn.report_copy(
+                                        format!(
+                                            "Downloaded {} ({}B)",
+                                            rx_path,
+                                            size_with_suffix(bytes)
+                                        ),
+                                        &rx_path,
+                                        &local_path.to_string_lossy(),
+                                        bytes,
+                                        verified,
+                                    );
+                                    let mut count = thread_completed.lock().unwrap();
+                                    *count += 1;
+                                }
+                                Err(err) => thread_conn.report_failure("download", &rx_path, err),
+                            }
                         }
-                        Err(err_msg) => quit_msg!("Failed to download {}: {}", rx_path, err_msg),
+                        Err(err_msg) => thread_conn.report_failure("download", &rx_path, err_msg),
                     }
                 }
                 thread_wg.done();
@@ -205,38 +541,546 @@ impl CpClient {
         }
 
         wg.wait();
-        println!(
-            "Finished downloading {} file(s)",
-            *completed.lock().unwrap()
-        );
+        self.write_manifest();
+        self.report_finished("downloading", *completed.lock().unwrap());
+        self.exit_if_failed("download");
     }
-}
 
-fn download_file(data_file: &DataFile, local_path: &str) -> Result<u64, String> {
-    match data_file.get() {
-        Ok(mut response) => {
-            let full_path = match fs::metadata(local_path) {
-                Ok(ref m) if m.is_dir() => {
-                    Path::new(local_path).join(data_file.basename().unwrap())
+    // Downloads a file, retrying with exponential backoff after errors that may be temporary
+    //   Retries resume from the end of the partially written file if the server supports it
+    fn download_with_retry(
+        &self,
+        data_file: &DataFile,
+        local_path: &str,
+        mut transfer: Transfer,
+    ) -> Result<u64, String> {
+        let path = local_dest_path(data_file, local_path);
+        let url = data_file.to_url().map_err(|err| err.to_string())?;
+        let mut attempt = 0;
+        // Only a file that an earlier attempt created or truncated is resumed, so that the rest of
+        //   the download is never appended to a pre-existing local file
+        let mut created = false;
+        loop {
+            match self.try_download(url.path(), &path, &mut created, &mut transfer) {
+                Ok(bytes) => return Ok(bytes),
+                Err(DownloadError::Transient(err)) if attempt < self.retries => {
+                    let delay = retry_delay(attempt);
+                    attempt += 1;
+                    self.progress.suspend(|| {
+                        eprintln!(
+                            "Error downloading {}: {} (retry {}/{} in {}s)",
+                            data_file.to_data_uri(),
+                            err,
+                            attempt,
+                            self.retries,
+                            delay.as_secs()
+                        )
+                    });
+                    thread::sleep(delay);
                 }
-                _ => Path::new(local_path).to_owned(),
-            };
+                Err(DownloadError::Transient(err)) if attempt > 0 => {
+                    return Err(format!("{} (after {} retries)", err, attempt))
+                }
+                Err(DownloadError::Transient(err)) | Err(DownloadError::Fatal(err)) => {
+                    return Err(err)
+                }
+            }
+        }
+    }
+
+    fn try_download(
+        &self,
+        url_path: &str,
+        path: &Path,
+        created: &mut bool,
+        transfer: &mut Transfer,
+    ) -> Result<u64, DownloadError> {
+        let offset = match fs::metadata(path) {
+            Ok(ref m) if *created => m.len(),
+            _ => 0,
+        };
+        let mut res = self
+            .api
+            .get_from(url_path, offset)
+            .map_err(|err| DownloadError::Transient(err.to_string()))?;
 
-            let mut output = match File::create(full_path) {
-                Ok(f) => Box::new(f),
-                Err(err) => return Err(format!("Error creating file: {}", err)),
+        let status = res.status();
+        if status == StatusCode::RANGE_NOT_SATISFIABLE {
+            // The partial file can't be resumed, so start over on the next attempt
+            let _ = fs::remove_file(path);
+            *created = false;
+            return Err(DownloadError::Transient(format!(
+                "Cannot resume download ({})",
+                status
+            )));
+        } else if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(DownloadError::Transient(format!(
+                "Server error ({})",
+                status
+            )));
+        } else if !status.is_success() {
+            return Err(DownloadError::Fatal(format!(
+                "Download failed ({})",
+                status
+            )));
+        } else if res.headers().get("X-Data-Type").map(|t| t.as_bytes()) == Some(b"directory") {
+            return Err(DownloadError::Fatal(
+                "Cannot download a directory (use -r to copy recursively)".to_string(),
+            ));
+        }
+
+        // Servers that ignore the Range header send the whole file
+        let resumed =
+            status == StatusCode::PARTIAL_CONTENT && content_range_start(&res) == Some(offset);
+        let (file, start) = if resumed {
+            (OpenOptions::new().append(true).open(path), offset)
+        } else {
+            (File::create(path), 0)
+        };
+        let mut file = file.map_err(|err| {
+            DownloadError::Fatal(format!("Error creating file {}: {}", path.display(), err))
+        })?;
+        *created = true;
+        let size = res.content_length().map(|len| start + len);
+        if let Some(size) = size {
+            transfer.set_size(size);
+        }
+        transfer.restart_at(start);
+
+        let mut written = start;
+        let mut buf = vec![0; COPY_CHUNK_SIZE];
+        loop {
+            let n = match res.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(err) => return Err(DownloadError::Transient(err.to_string())),
             };
+            file.write_all(&buf[..n]).map_err(|err| {
+                DownloadError::Fatal(format!("Error writing {}: {}", path.display(), err))
+            })?;
+            written += n as u64;
+            transfer.add(n as u64);
+        }
 
-            // Copy downloaded data to the output writer
-            match io::copy(&mut response, &mut output) {
-                Ok(bytes) => Ok(bytes),
-                Err(err) => Err(format!("Error copying data: {}", err)),
+        match size {
+            Some(size) if written < size => Err(DownloadError::Transient(format!(
+                "Connection closed after {} of {} bytes",
+                written, size
+            ))),
+            _ => Ok(written),
+        }
+    }
+
+    // Determines the local directory that a remote directory is copied into
+    //   Like `cp -r`, copying into an existing directory nests the source under its own name
+    fn local_root(&self, remote_dir: &DataDir) -> PathBuf {
+        let dest = Path::new(&*self.dest);
+        let root = match fs::metadata(dest) {
+            Ok(ref m) if m.is_dir() => match remote_dir.basename() {
+                Some(ref name) if !name.is_empty() => dest.join(name),
+                _ => dest.to_owned(),
+            },
+            Ok(_) => quit_msg!(
+                "Cannot overwrite file {} with directory {}",
+                dest.display(),
+                remote_dir.to_data_uri()
+            ),
+            Err(_) => dest.to_owned(),
+        };
+        create_local_dir(&root);
+        root
+    }
+
+    fn recurse_remote_and_send(
+        &self,
+        tx: &Sender<CopyJob>,
+        remote_dir: &DataDir,
+        local_dir: &Path,
+    ) {
+        for entry_result in remote_dir.list() {
+            match entry_result {
+                Ok(DataItem::Dir(d)) => {
+                    let child_dir = local_dir.join(d.basename().unwrap());
+                    create_local_dir(&child_dir);
+                    self.recurse_remote_and_send(tx, &d, &child_dir);
+                }
+                Ok(DataItem::File(f)) => self.send(
+                    tx,
+                    f.to_data_uri(),
+                    local_dir.to_string_lossy().into_owned(),
+                    Some(f.size),
+                ),
+                // A failed listing page would be requested again, so skip the rest of the directory
+                Err(err) => {
+                    let err = format!("Error listing directory: {}", err);
+                    return self.report_failure("download", &remote_dir.to_data_uri(), err);
+                }
             }
         }
-        Err(e) => Err(format!(
-            "Error downloading ({}): {}",
-            data_file.to_data_uri(),
-            e
-        )),
+    }
+
+    fn copy_remote(&self, sources: Vec<String>) {
+        let concurrency = self.concurrency(sources.len());
+
+        let (tx, rx) = chan::sync(self.max_concurrency as usize);
+        let wg = chan::WaitGroup::new();
+        let completed = Arc::new(Mutex::new(0));
+
+        // One Producer thread queuing up file paths to copy
+        let producer_conn = self.clone();
+        thread::spawn(move || {
+            for path in sources {
+                if !producer_conn.recursive {
+                    producer_conn.send(&tx, path, producer_conn.dest.to_string(), None);
+                    continue;
+                }
+                match producer_conn.client.data(&path).into_type() {
+                    Ok(DataItem::Dir(d)) => {
+                        let root = remote_dest_dir(
+                            &producer_conn.client,
+                            &producer_conn.dest,
+                            &path,
+                            d.basename(),
+                        );
+                        create_remote_dir(&root);
+                        producer_conn.recurse_remote_dirs_and_send(&tx, &d, &root);
+                    }
+                    // Let the copy report any errors for files or missing paths
+                    _ => producer_conn.send(&tx, path, producer_conn.dest.to_string(), None),
+                }
+            }
+            drop(tx);
+        });
+
+        // Spin up threads to concurrently copy files per that paths received on rx channel
+        for _ in 0..concurrency {
+            wg.add(1);
+
+            let thread_wg = wg.clone();
+            let thread_rx = rx.clone();
+            let thread_conn = self.clone();
+            let thread_completed = completed.clone();
+
+            thread::spawn(move || {
+                for (rx_path, rx_dest, rx_size) in thread_rx {
+                    let source = thread_conn.client.file(&rx_path);
+                    let filename = source.basename().unwrap_or_default();
+                    let dest = remote_dest_file(&thread_conn.client, &rx_dest, &filename);
+                    let transfer = Transfer::start(&thread_conn.progress, &rx_path, rx_size);
+                    match copy_remote_file(&thread_conn.client, &source, &dest, Some(transfer)) {
+                        Ok(bytes) => match thread_conn.check_remote(&source, &dest) {
+                            Ok(verified) => {
+                                let (src_uri, dest_uri) =
+                                    (source.to_data_uri(), dest.to_data_uri());
+                                thread_conn.report_copy(
+                                    format!(
+                                        "Copied {} to {} ({}B)",
+                                        src_uri,
+                                        dest_uri,
+                                        size_with_suffix(bytes)
+                                    ),
+                                    &src_uri,
+                                    &dest_uri,
+                                    bytes,
+                                    verified,
+                                );
+                                let mut count = thread_completed.lock().unwrap();
+                                *count += 1;
+                            }
+                            Err(err) => thread_conn.report_failure("copy", &rx_path, err),
+                        },
+                        Err(err_msg) => thread_conn.report_failure("copy", &rx_path, err_msg),
+                    }
+                }
+                thread_wg.done();
+            });
+        }
+
+        wg.wait();
+        self.report_finished("copying", *completed.lock().unwrap());
+        self.exit_if_failed("copy");
+    }
+
+    fn recurse_remote_dirs_and_send(
+        &self,
+        tx: &Sender<CopyJob>,
+        remote_dir: &DataDir,
+        dest_dir: &DataDir,
+    ) {
+        for entry_result in remote_dir.list() {
+            match entry_result {
+                Ok(DataItem::Dir(d)) => {
+                    let child_dir: DataDir = dest_dir.child(&d.basename().unwrap());
+                    create_remote_dir(&child_dir);
+                    self.recurse_remote_dirs_and_send(tx, &d, &child_dir);
+                }
+                Ok(DataItem::File(f)) => {
+                    self.send(tx, f.to_data_uri(), dest_dir.to_data_uri(), Some(f.size))
+                }
+                // A failed listing page would be requested again, so skip the rest of the directory
+                Err(err) => {
+                    let err = format!("Error listing directory: {}", err);
+                    return self.report_failure("copy", &remote_dir.to_data_uri(), err);
+                }
+            }
+        }
+    }
+}
+
+// Determines the remote file that a file named `filename` is copied to
+//   If dest is an existing directory, the file is added to it, otherwise dest is the file path
+pub(super) fn remote_dest_file(client: &Algorithmia, dest: &str, filename: &str) -> DataFile {
+    match client.data(dest).into_type() {
+        Ok(DataItem::Dir(d)) => d.child(filename),
+        _ => client.file(dest),
+    }
+}
+
+// Determines the remote directory that a directory named `dirname` is copied to
+//   Like `cp -r`, copying into an existing directory nests the source under its own name
+pub(super) fn remote_dest_dir(
+    client: &Algorithmia,
+    dest: &str,
+    source: &str,
+    dirname: Option<String>,
+) -> DataDir {
+    let dest_dir = client.dir(dest);
+    match client.data(dest).into_type() {
+        Ok(DataItem::Dir(_)) => match dirname {
+            Some(ref name) if !name.is_empty() => dest_dir.child(name),
+            _ => dest_dir,
+        },
+        Ok(DataItem::File(f)) => quit_msg!(
+            "Cannot overwrite file {} with directory {}",
+            f.to_data_uri(),
+            source
+        ),
+        Err(_) => dest_dir,
+    }
+}
+
+pub(super) fn create_remote_dir(dir: &DataDir) {
+    match dir.exists() {
+        Ok(true) => (),
+        _ => {
+            if let Err(err) = dir.create(DataAcl::default()) {
+                quit_err!("Error creating directory {}: {}", dir.to_data_uri(), err);
+            }
+        }
+    }
+}
+
+pub(super) fn create_local_dir(dir: &Path) {
+    if let Err(err) = fs::create_dir_all(dir) {
+        quit_err!("Error creating directory {}: {}", dir.display(), err);
+    }
+}
+
+// Streams a remote file to another remote path without buffering it locally, returning the bytes copied
+//   The copy fails, rather than storing a different file, unless exactly the source's size is read
+pub(super) fn copy_remote_file(
+    client: &Algorithmia,
+    source: &DataFile,
+    dest: &DataFile,
+    mut transfer: Option<Transfer>,
+) -> Result<u64, String> {
+    let mut data = match source.get() {
+        Ok(data) => data,
+        Err(err) => {
+            return Err(format!(
+                "Error downloading {}: {}",
+                source.to_data_uri(),
+                err
+            ))
+        }
+    };
+
+    if let Some(ref mut transfer) = transfer {
+        transfer.set_size(data.size);
+    }
+
+    // The download isn't Send, so it's read on this thread while the upload reads from a channel
+    let (tx, rx) = mpsc::sync_channel(COPY_BUFFERS);
+    let reader = ChannelReader {
+        rx,
+        buf: Vec::new(),
+        pos: 0,
+    };
+    let body = Body::sized(reader, data.size);
+    let dest_file = client.file(&dest.to_data_uri());
+    let upload = thread::spawn(move || dest_file.put(body).map_err(|err| err.to_string()));
+
+    let mut copied = 0;
+    let mut mismatch = None;
+    loop {
+        let mut chunk = vec![0; COPY_CHUNK_SIZE];
+        let res = match data.read(&mut chunk) {
+            Ok(0) if copied == data.size => break,
+            Ok(n) if n == 0 || copied + n as u64 > data.size => {
+                let read = match n {
+                    0 => copied.to_string(),
+                    _ => format!("more than {}", data.size),
+                };
+                mismatch = Some(format!(
+                    "Error copying {}: read {} bytes, but it has {}",
+                    source.to_data_uri(),
+                    read,
+                    data.size
+                ));
+                Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "download size doesn't match the source",
+                ))
+            }
+            Ok(n) => {
+                copied += n as u64;
+                if let Some(ref transfer) = transfer {
+                    transfer.add(n as u64);
+                }
+                chunk.truncate(n);
+                Ok(chunk)
+            }
+            Err(err) => Err(err),
+        };
+        // Errors are forwarded so that the upload fails rather than storing a truncated file
+        let is_err = res.is_err();
+        if tx.send(res).is_err() || is_err {
+            break;
+        }
+    }
+    drop(tx);
+
+    let uploaded = upload.join();
+    if let Some(err) = mismatch {
+        return Err(err);
+    }
+    match uploaded {
+        Ok(Ok(())) => Ok(copied),
+        Ok(Err(err)) => Err(format!("Error uploading {}: {}", dest.to_data_uri(), err)),
+        Err(_) => Err(format!("Error uploading {}", dest.to_data_uri())),
+    }
+}
+
+// Why an attempt to download a file failed
+enum DownloadError {
+    // e.g. a dropped connection or server error, which may succeed if retried
+    Transient(String),
+    Fatal(String),
+}
+
+// Waits 1s before the first retry, doubling each time up to 30s
+fn retry_delay(attempt: u32) -> Duration {
+    Duration::from_secs(cmp::min(1 << cmp::min(attempt, 5), 30))
+}
+
+// The first byte of a partial response, from e.g. "Content-Range: bytes 100-199/200"
+fn content_range_start(res: &Response) -> Option<u64> {
+    let range = res.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    let start = range.trim_start_matches("bytes ").split('-').next()?;
+    start.parse().ok()
+}
+
+const COPY_CHUNK_SIZE: usize = 64 * 1024;
+const COPY_BUFFERS: usize = 4;
+
+// Reads chunks sent from another thread, e.g. to use a download as an upload body
+struct ChannelReader {
+    rx: Receiver<io::Result<Vec<u8>>>,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.buf.len() {
+            match self.rx.recv() {
+                Ok(chunk) => {
+                    self.buf = chunk?;
+                    self.pos = 0;
+                }
+                // Sender finished
+                Err(_) => return Ok(0),
+            }
+        }
+        let n = cmp::min(out.len(), self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+// The local file that a download writes to, which is inside `local_path` if it's a directory
+fn local_dest_path(data_file: &DataFile, local_path: &str) -> PathBuf {
+    match fs::metadata(local_path) {
+        Ok(ref m) if m.is_dir() => Path::new(local_path).join(data_file.basename().unwrap()),
+        _ => Path::new(local_path).to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{env, process};
+
+    #[test]
+    fn test_download_retry_replaces_stale_file() {
+        // Stands in for the Data API, failing the first request before sending any of the file
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", server.server_addr());
+        let api = thread::spawn(move || {
+            let mut ranges = Vec::new();
+            for status in &[500, 200] {
+                let request = server.recv().unwrap();
+                let range = request
+                    .headers()
+                    .iter()
+                    .find(|h| h.field.equiv("Range"))
+                    .map(|h| h.value.to_string());
+                ranges.push(range);
+                let response = tiny_http::Response::from_string("new contents");
+                request.respond(response.with_status_code(*status)).unwrap();
+            }
+            ranges
+        });
+
+        let path = env::temp_dir().join(format!("mia-cp-stale-{}.txt", process::id()));
+        fs::write(&path, "stale local contents").unwrap();
+        let client = Algorithmia::client_with_url("simTestKey", &*base_url).unwrap();
+        let cp_client = CpClient::for_transfers(
+            client.clone(),
+            ApiClient::new(&base_url, "simTestKey"),
+            1,
+            1,
+        );
+
+        let source = client.file("data://.my/foo/stale.txt");
+        let downloaded = cp_client.download_file(&source, &path.to_string_lossy(), None);
+        let contents = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(&path);
+
+        // The retry downloads the whole file rather than resuming from the stale file's size
+        assert_eq!(api.join().unwrap(), vec![None, None]);
+        assert_eq!(downloaded, Ok(12));
+        assert_eq!(contents, "new contents");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_recursive_upload_skips_symlinked_dirs() {
+        let dir = env::temp_dir().join(format!("mia-cp-symlink-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("loop")).unwrap();
+
+        let client = Algorithmia::client_with_url("simTestKey", "http://127.0.0.1:1").unwrap();
+        let api = ApiClient::new("http://127.0.0.1:1", "simTestKey");
+        let cp_client = CpClient::for_transfers(client.clone(), api, 1, 0);
+        let (tx, rx) = chan::sync(8);
+        cp_client.recurse_and_send(&tx, &dir, &client.dir("data://.my/foo"));
+        drop(tx);
+        let sent: Vec<String> = rx.iter().map(|(source, _, _)| source).collect();
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(sent, vec![dir.join("a.txt").to_string_lossy().into_owned()]);
     }
 }
@@ -0,0 +1,161 @@
+use crate::config::Profile;
+use crate::output::{self, Records};
+use crate::{data, CmdRunner};
+use algorithmia::data::{DataItem, HasDataPath};
+use algorithmia::Algorithmia;
+use docopt::Docopt;
+use rustc_serialize::json::ToJson;
+use std::cmp::Reverse;
+use std::vec::IntoIter;
+
+static USAGE: &str = r##"Usage:
+  mia du [options] <data-dir>...
+
+  Summarize the storage used by data directories via the Agorithmia Data API
+
+  Prints the total size in bytes of each directory, including its subdirectories
+
+  <data-dir>    Specifies the Algorithmia Data URI
+                The 'data://' prefix is optional
+
+  Options:
+    -s, --summarize         Only print the total of each <data-dir>
+    -h, --human-readable    Print sizes with k, M, and G suffixes
+    --max-depth <depth>     Only print totals for directories at most <depth> levels below <data-dir>
+    --sort                  Sort by size, largest first
+
+  Examples:
+    mia du -sh .my/foo .my/bar                           Print the total size of 2 directories
+    mia du -h --max-depth 1 --sort .my                   Find your largest directories
+"##;
+
+#[derive(RustcDecodable, Debug)]
+struct Args {
+    arg_data_dir: Vec<String>,
+    flag_summarize: bool,
+    flag_human_readable: bool,
+    flag_max_depth: Option<usize>,
+    flag_sort: bool,
+}
+
+// Total size of a directory, including its subdirectories
+struct DirUsage {
+    data_uri: String,
+    depth: usize,
+    bytes: u64,
+}
+
+pub struct Du {
+    client: Algorithmia,
+}
+impl CmdRunner for Du {
+    fn get_usage() -> &'static str {
+        USAGE
+    }
+
+    fn cmd_main(&self, argv: IntoIter<String>) {
+        let args: Args = Docopt::new(USAGE)
+            .and_then(|d| d.argv(argv).decode())
+            .unwrap_or_else(|e| e.exit());
+
+        let max_depth = if args.flag_summarize {
+            Some(0)
+        } else {
+            args.flag_max_depth
+        };
+
+        let mut failed = false;
+        let mut usages = Vec::new();
+        for path in &args.arg_data_dir {
+            match self.usage(path) {
+                Ok(dirs) => usages.extend(
+                    dirs.into_iter()
+                        .filter(|u| max_depth.map(|max| u.depth <= max).unwrap_or(true)),
+                ),
+                Err(err) => {
+                    eprintln_red!("Error reading {}: {}", path, err);
+                    failed = true;
+                }
+            }
+        }
+
+        if args.flag_sort {
+            usages.sort_by_key(|u| Reverse(u.bytes));
+        }
+        let records = Records::new();
+        for usage in usages {
+            let size = if args.flag_human_readable {
+                data::size_with_suffix(usage.bytes)
+            } else {
+                usage.bytes.to_string()
+            };
+            records.report(
+                &format!("{}\t{}", size, usage.data_uri),
+                output::object(vec![
+                    ("data_uri", usage.data_uri.to_json()),
+                    ("bytes", usage.bytes.to_json()),
+                    ("depth", usage.depth.to_json()),
+                ]),
+            );
+        }
+        records.finish();
+
+        if failed {
+            ::std::process::exit(1);
+        }
+    }
+}
+
+impl Du {
+    pub fn new(profile: Profile) -> Self {
+        Du {
+            client: profile.client(),
+        }
+    }
+
+    // Totals every directory in the tree, ordering subdirectories before their parents
+    fn usage(&self, path: &str) -> Result<Vec<DirUsage>, String> {
+        let root = self.client.dir(path);
+
+        // Directories being walked, from the root to the current directory
+        let mut stack = vec![DirUsage {
+            data_uri: root.to_data_uri(),
+            depth: 0,
+            bytes: 0,
+        }];
+        let mut totals = Vec::new();
+        data::walk_dir(&root, None, &mut |item, depth, _| {
+            while stack.len() > depth {
+                pop_dir(&mut stack, &mut totals);
+            }
+            match *item {
+                DataItem::Dir(ref d) => stack.push(DirUsage {
+                    data_uri: d.to_data_uri(),
+                    depth,
+                    bytes: 0,
+                }),
+                DataItem::File(ref f) => {
+                    if let Some(dir) = stack.last_mut() {
+                        dir.bytes += f.size;
+                    }
+                }
+            }
+        })
+        .map_err(|err| err.to_string())?;
+
+        while !stack.is_empty() {
+            pop_dir(&mut stack, &mut totals);
+        }
+        Ok(totals)
+    }
+}
+
+// Finishes the current directory, adding its total to its parent
+fn pop_dir(stack: &mut Vec<DirUsage>, totals: &mut Vec<DirUsage>) {
+    if let Some(dir) = stack.pop() {
+        if let Some(parent) = stack.last_mut() {
+            parent.bytes += dir.bytes;
+        }
+        totals.push(dir);
+    }
+}
@@ -0,0 +1,314 @@
+use crate::api::ApiClient;
+use crate::config::Profile;
+use crate::output::{self, Records};
+use crate::{data, CmdRunner};
+use algorithmia::data::{DataItem, HasDataPath};
+use algorithmia::Algorithmia;
+use docopt::Docopt;
+use glob::Pattern;
+use regex::Regex;
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::vec::IntoIter;
+
+static USAGE: &str = r##"Usage:
+  mia find [options] <data-dir>
+
+  Recursively search a data directory via the Agorithmia Data API
+
+  Prints the data URI of every file and directory below <data-dir> that matches all of the filters
+
+  <data-dir>    Specifies the Algorithmia Data URI
+                The 'data://' prefix is optional
+
+  Options:
+    --name <pattern>        Name matches a glob pattern (e.g. '*.jpg')
+    --regex <regex>         Name matches a regular expression (e.g. '^img_[0-9]+')
+    --type <type>           Only files (f) or directories (d)
+    --size <size>           File is <size> bytes, or more (+<size>) or less (-<size>) than it,
+                              with an optional k, M, or G suffix (e.g. +10M)
+    --mtime <days>          File was modified <days> days ago, or more (+<days>) or less (-<days>)
+    --newer <data-file>     File was modified more recently than <data-file>
+    --max-depth <depth>     Descend at most <depth> directory levels
+    --print0                Separate results with NUL instead of newline (e.g. for 'xargs -0')
+
+  Examples:
+    mia find .my/foo --name '*.jpg' --size +10M          Find JPEGs larger than 10M
+    mia find .my/foo --type f --mtime=-1                 Find files modified in the last day
+    mia find .my/foo --name '*.tmp' --print0 | xargs -0 mia rm
+                                                         Remove temporary files
+"##;
+
+#[derive(RustcDecodable, Debug)]
+struct Args {
+    arg_data_dir: String,
+    flag_name: Option<String>,
+    flag_regex: Option<String>,
+    flag_type: Option<String>,
+    flag_size: Option<String>,
+    flag_mtime: Option<String>,
+    flag_newer: Option<String>,
+    flag_max_depth: Option<usize>,
+    flag_print0: bool,
+}
+
+// Comparison parsed from the optional +/- prefix of `--size` and `--mtime`
+#[derive(Debug, PartialEq)]
+enum Bound {
+    Over(u64),
+    Under(u64),
+    Exactly(u64),
+}
+
+impl Bound {
+    // Splits off the +/- prefix, returning the kind of bound and the rest of the value
+    fn split(value: &str) -> (fn(u64) -> Bound, &str) {
+        match value.chars().next() {
+            Some('+') => (Bound::Over, &value[1..]),
+            Some('-') => (Bound::Under, &value[1..]),
+            _ => (Bound::Exactly, value),
+        }
+    }
+
+    // Like `find`, values are compared in whole units, rounding up (e.g. 1 byte is 1k)
+    fn matches(&self, value: u64, unit: u64) -> bool {
+        let n = match value {
+            0 => 0,
+            v => (v - 1) / unit + 1,
+        };
+        match *self {
+            Bound::Over(bound) => n > bound,
+            Bound::Under(bound) => n < bound,
+            Bound::Exactly(bound) => n == bound,
+        }
+    }
+}
+
+// Parses a size into (count, unit size in bytes)
+fn parse_size(size: &str) -> Option<(u64, u64)> {
+    let (count, unit) = match size.chars().last() {
+        Some('k') | Some('K') => (&size[..size.len() - 1], 1024),
+        Some('M') => (&size[..size.len() - 1], 1024 * 1024),
+        Some('G') => (&size[..size.len() - 1], 1024 * 1024 * 1024),
+        _ => (size, 1),
+    };
+    count.parse().ok().map(|count| (count, unit))
+}
+
+enum ItemType {
+    File,
+    Dir,
+}
+
+#[derive(Default)]
+struct Filters {
+    name: Option<Pattern>,
+    regex: Option<Regex>,
+    item_type: Option<ItemType>,
+    // (bound, unit)
+    size: Option<(Bound, u64)>,
+    mtime: Option<Bound>,
+    newer_than: Option<i64>,
+}
+
+impl Filters {
+    // Both `--name` and `--regex` match the basename, but the regex doesn't have to match all of it
+    fn name_matches(&self, name: &str) -> bool {
+        let glob_matches = self.name.as_ref().map_or(true, |glob| glob.matches(name));
+        let regex_matches = self
+            .regex
+            .as_ref()
+            .map_or(true, |regex| regex.is_match(name));
+        glob_matches && regex_matches
+    }
+
+    fn matches(&self, item: &DataItem, now: i64) -> bool {
+        let name = match *item {
+            DataItem::File(ref f) => f.basename(),
+            DataItem::Dir(ref d) => d.basename(),
+        };
+        if !self.name_matches(&name.unwrap_or_default()) {
+            return false;
+        }
+        match (&self.item_type, item) {
+            (&Some(ItemType::File), &DataItem::Dir(_)) => return false,
+            (&Some(ItemType::Dir), &DataItem::File(_)) => return false,
+            _ => (),
+        }
+
+        // Sizes and modification times are only known for files
+        let has_file_filters =
+            self.size.is_some() || self.mtime.is_some() || self.newer_than.is_some();
+        match *item {
+            DataItem::Dir(_) => !has_file_filters,
+            DataItem::File(ref f) => {
+                let modified = f.last_modified.timestamp();
+                if let Some((ref bound, unit)) = self.size {
+                    if !bound.matches(f.size, unit) {
+                        return false;
+                    }
+                }
+                if let Some(ref bound) = self.mtime {
+                    // Like `find`, partial days are ignored when counting how many days ago
+                    let days_ago = (now - modified).max(0) as u64 / SECONDS_PER_DAY;
+                    if !bound.matches(days_ago, 1) {
+                        return false;
+                    }
+                }
+                match self.newer_than {
+                    Some(newer_than) => modified > newer_than,
+                    None => true,
+                }
+            }
+        }
+    }
+}
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+pub struct Find {
+    client: Algorithmia,
+    api: ApiClient,
+}
+impl CmdRunner for Find {
+    fn get_usage() -> &'static str {
+        USAGE
+    }
+
+    fn cmd_main(&self, argv: IntoIter<String>) {
+        let args: Args = Docopt::new(USAGE)
+            .and_then(|d| d.argv(argv).decode())
+            .unwrap_or_else(|e| e.exit());
+
+        let filters = self.filters(&args);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        let separator = if args.flag_print0 { '\0' } else { '\n' };
+
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        let records = Records::new();
+        let my_dir = self.client.dir(&args.arg_data_dir);
+        let res = data::walk_dir(&my_dir, args.flag_max_depth, &mut |item, _, _| {
+            if !filters.matches(item, now) {
+                return;
+            }
+            if !output::is_text() {
+                return records.add(data::item_record(item));
+            }
+            let data_uri = match *item {
+                DataItem::File(ref f) => f.to_data_uri(),
+                DataItem::Dir(ref d) => d.to_data_uri(),
+            };
+            let _ = write!(out, "{}{}", data_uri, separator);
+        });
+        let _ = out.flush();
+        if let Err(err) = res {
+            quit_msg!("Error searching {}: {}", args.arg_data_dir, err);
+        }
+        records.finish();
+    }
+}
+
+impl Find {
+    pub fn new(profile: Profile) -> Self {
+        Find {
+            client: profile.client(),
+            api: profile.api_client(),
+        }
+    }
+
+    fn filters(&self, args: &Args) -> Filters {
+        let mut filters = Filters::default();
+        if let Some(ref name) = args.flag_name {
+            filters.name = Some(
+                Pattern::new(name)
+                    .unwrap_or_else(|err| quit_msg!("Invalid pattern '{}': {}", name, err)),
+            );
+        }
+        if let Some(ref regex) = args.flag_regex {
+            filters.regex = Some(
+                Regex::new(regex)
+                    .unwrap_or_else(|err| quit_msg!("Invalid regex '{}': {}", regex, err)),
+            );
+        }
+        if let Some(ref item_type) = args.flag_type {
+            filters.item_type = match &**item_type {
+                "f" => Some(ItemType::File),
+                "d" => Some(ItemType::Dir),
+                _ => quit_msg!("Invalid type '{}'. Expected one of: f, d", item_type),
+            };
+        }
+        if let Some(ref size) = args.flag_size {
+            let (bound, rest) = Bound::split(size);
+            filters.size = match parse_size(rest) {
+                Some((count, unit)) => Some((bound(count), unit)),
+                None => quit_msg!("Invalid size '{}'. Expected e.g. 100, +10M, or -1G", size),
+            };
+        }
+        if let Some(ref days) = args.flag_mtime {
+            let (bound, rest) = Bound::split(days);
+            filters.mtime = match rest.parse() {
+                Ok(days) => Some(bound(days)),
+                Err(_) => quit_msg!("Invalid number of days '{}'", days),
+            };
+        }
+        // Resolved once here, rather than for each file compared against it
+        if let Some(ref path) = args.flag_newer {
+            filters.newer_than = Some(self.last_modified(path));
+        }
+        filters
+    }
+
+    fn last_modified(&self, path: &str) -> i64 {
+        let file = self.client.file(path);
+        match data::file_modified(&self.api, &file) {
+            Ok(Some(modified)) => modified.timestamp(),
+            Ok(None) => quit_msg!("File not found: {}", file.to_data_uri()),
+            Err(err) => quit_msg!("Error reading {}: {}", path, err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_size_bounds() {
+        let size = |s| {
+            let (bound, rest) = Bound::split(s);
+            let (count, unit) = parse_size(rest).unwrap();
+            (bound(count), unit)
+        };
+
+        assert_eq!(size("+10M"), (Bound::Over(10), 1024 * 1024));
+        assert_eq!(size("-2k"), (Bound::Under(2), 1024));
+        assert_eq!(size("100"), (Bound::Exactly(100), 1));
+        assert_eq!(parse_size("10X"), None);
+
+        // Sizes round up to whole units
+        assert!(Bound::Exactly(1).matches(1, 1024));
+        assert!(Bound::Over(10).matches(10 * 1024 * 1024 + 1, 1024 * 1024));
+        assert!(!Bound::Over(10).matches(10 * 1024 * 1024, 1024 * 1024));
+        assert!(Bound::Under(2).matches(1024, 1024));
+    }
+
+    #[test]
+    fn test_name_matches() {
+        let filters = |name: Option<&str>, regex: Option<&str>| Filters {
+            name: name.map(|name| Pattern::new(name).unwrap()),
+            regex: regex.map(|regex| Regex::new(regex).unwrap()),
+            ..Filters::default()
+        };
+
+        assert!(filters(None, Some("^foo")).name_matches("foo_bar.jpg"));
+        assert!(!filters(None, Some("^foo")).name_matches("bar_foo.jpg"));
+        assert!(filters(None, Some(r"\.jpe?g$")).name_matches("cat.jpeg"));
+        assert!(filters(Some("*.jpg"), Some("^cat")).name_matches("cat.jpg"));
+        assert!(!filters(Some("*.jpg"), Some("^cat")).name_matches("dog.jpg"));
+        assert!(filters(None, None).name_matches("anything"));
+    }
+}
@@ -1,9 +1,13 @@
+use crate::api::ApiClient;
 use crate::config::Profile;
+use crate::output::{self, Records};
 use crate::{color_choice, data, CmdRunner};
-use algorithmia::data::{DataItem, HasDataPath};
+use algorithmia::data::{DataDir, DataItem, HasDataPath};
 use algorithmia::Algorithmia;
 use docopt::Docopt;
+use rustc_serialize::json::ToJson;
 use std::cmp;
+use std::collections::{BTreeMap, HashMap};
 use std::io::Write;
 use std::ops::Deref;
 use std::vec::IntoIter;
@@ -21,17 +25,26 @@ static USAGE: &'static str = r##"Usage:
                 Defaults to 'data://' root path
 
   Options:
-    -l          Use long listing format
+    -l                      Use long listing format (includes the read ACL of directories)
+    -R, --recursive         List subdirectories recursively
+    --max-depth <depth>     Descend at most <depth> directory levels (implies -R)
+
+  Examples:
+    mia ls -l .my/foo                                    List 'foo' with sizes and permissions
+    mia ls -R --max-depth 2 .my/foo                      List 'foo' and its direct subdirectories
 "##;
 
 #[derive(RustcDecodable, Debug)]
 struct Args {
     arg_data_dir: Option<String>,
     flag_l: bool,
+    flag_recursive: bool,
+    flag_max_depth: Option<usize>,
 }
 
 pub struct Ls {
     client: Algorithmia,
+    api: ApiClient,
 }
 
 impl CmdRunner for Ls {
@@ -49,7 +62,29 @@ impl CmdRunner for Ls {
             .as_ref()
             .map(Deref::deref)
             .unwrap_or("data://");
-        self.list_dir(data_uri, args.flag_l);
+        let my_dir = self.client.dir(data_uri);
+        let recursive = args.flag_recursive || args.flag_max_depth.is_some();
+        if !output::is_text() {
+            return self.list_records(&my_dir, args.flag_l, recursive, args.flag_max_depth);
+        }
+
+        let mut t_out = StandardStream::stdout(color_choice());
+        let mut acls = DirAcls::default();
+        if recursive {
+            let mut summary = data::Summary::default();
+            self.list_dir_recursive(
+                &mut t_out,
+                &my_dir,
+                args.flag_l,
+                (1, args.flag_max_depth),
+                &mut acls,
+                &mut summary,
+            );
+            let _ = writeln!(t_out, "\n{}", summary);
+        } else {
+            let items = list_items(&my_dir);
+            self.print_items(&mut t_out, &items, args.flag_l, &mut acls);
+        }
     }
 }
 
@@ -57,44 +92,101 @@ impl Ls {
     pub fn new(profile: Profile) -> Self {
         Ls {
             client: profile.client(),
+            api: profile.api_client(),
         }
     }
 
-    fn list_dir(&self, path: &str, long: bool) {
-        let my_dir = self.client.dir(path);
+    // Structured output of the same items as the text listing
+    fn list_records(&self, dir: &DataDir, long: bool, recursive: bool, max_depth: Option<usize>) {
+        let records = Records::new();
+        let mut acls = DirAcls::default();
+        let mut add = |item: &DataItem| {
+            let mut record = data::item_record(item);
+            match *item {
+                DataItem::Dir(ref d) if long => {
+                    let acl = acls.get(&self.api, d);
+                    output::insert(&mut record, "acl", acl.to_json());
+                }
+                _ => (),
+            }
+            records.add(record);
+        };
 
-        let mut t_out = StandardStream::stdout(color_choice());
+        if recursive {
+            let res = data::walk_dir(dir, max_depth, &mut |item, _, _| add(item));
+            if let Err(err) = res {
+                quit_msg!("Error listing directory: {}", err);
+            }
+        } else {
+            list_items(dir).iter().for_each(&mut add);
+        }
+        records.finish();
+    }
+
+    // Lists a directory followed by each of its subdirectories, like `ls -R`
+    //   `depth` is (current depth, max depth) where the directory's own items are at depth 1
+    fn list_dir_recursive(
+        &self,
+        t_out: &mut StandardStream,
+        dir: &DataDir,
+        long: bool,
+        depth: (usize, Option<usize>),
+        acls: &mut DirAcls,
+        summary: &mut data::Summary,
+    ) {
+        let items = list_items(dir);
+        let _ = writeln!(t_out, "{}:", dir.to_data_uri());
+        self.print_items(t_out, &items, long, acls);
+        for item in &items {
+            summary.add(item);
+        }
+
+        let (current, max_depth) = depth;
+        if max_depth.map(|max| current < max).unwrap_or(true) {
+            for item in &items {
+                if let DataItem::Dir(ref d) = *item {
+                    let _ = writeln!(t_out);
+                    let depth = (current + 1, max_depth);
+                    self.list_dir_recursive(t_out, d, long, depth, acls, summary);
+                }
+            }
+        }
+    }
+
+    fn print_items(
+        &self,
+        t_out: &mut StandardStream,
+        items: &[DataItem],
+        long: bool,
+        acls: &mut DirAcls,
+    ) {
         if long {
-            for entry_result in my_dir.list() {
-                match entry_result {
-                    Ok(DataItem::Dir(d)) => {
-                        let _ = write!(t_out, "{:19} {:>5} ", "--         --", "[dir]");
+            for item in items {
+                match *item {
+                    DataItem::Dir(ref d) => {
+                        let acl = acls.get(&self.api, d).unwrap_or_else(|| "?".to_string());
+                        let _ = write!(t_out, "{:19} {:>5} {:8} ", "--         --", "[dir]", acl);
                         let _ = t_out.set_color(ColorSpec::new().set_fg(Some(Color::Blue)));
                         let _ = writeln!(t_out, "{}", d.basename().unwrap());
                         let _ = t_out.reset();
                     }
-                    Ok(DataItem::File(f)) => {
+                    DataItem::File(ref f) => {
                         let name = f.basename().unwrap();
                         let _ = write!(
                             t_out,
-                            "{:19} {:>5} ",
+                            "{:19} {:>5} {:8} ",
                             f.last_modified.format("%Y-%m-%d %H:%M:%S"),
-                            data::size_with_suffix(f.size)
+                            data::size_with_suffix(f.size),
+                            "--"
                         );
                         let c = FileType::from_filename(&name).to_color();
                         let _ = t_out.set_color(ColorSpec::new().set_fg(c));
                         let _ = writeln!(t_out, "{}", name);
                         let _ = t_out.reset();
                     }
-                    Err(err) => quit_err!("Error listing directory: {}", err),
                 }
             }
         } else {
-            let items: Vec<DataItem> = my_dir
-                .list()
-                .collect::<Result<Vec<_>, _>>()
-                .unwrap_or_else(|err| quit_err!("Error listing directory: {}", err));
-
             let width = match terminal_size() {
                 Some((Width(w), _)) => w as usize,
                 _ => 80, // default terminal width if we can't calculate it
@@ -115,15 +207,15 @@ impl Ls {
                     let _ = writeln!(t_out, "");
                     offset = 0;
                 }
-                let char_count = match item {
-                    DataItem::Dir(d) => {
+                let char_count = match *item {
+                    DataItem::Dir(ref d) => {
                         let name = d.basename().unwrap();
                         let _ = t_out.set_color(ColorSpec::new().set_fg(Some(Color::Blue)));
                         let _ = write!(t_out, "{}", name);
                         let _ = t_out.reset();
                         name.chars().count()
                     }
-                    DataItem::File(f) => {
+                    DataItem::File(ref f) => {
                         let name = f.basename().unwrap();
                         let c = FileType::from_filename(&name).to_color();
                         let _ = t_out.set_color(ColorSpec::new().set_fg(c));
@@ -144,7 +236,31 @@ impl Ls {
     }
 }
 
-enum FileType {
+// Read ACLs of listed directories, by their parent's URI, from one listing of each parent
+#[derive(Default)]
+struct DirAcls(HashMap<String, BTreeMap<String, Vec<String>>>);
+
+impl DirAcls {
+    fn get(&mut self, api: &ApiClient, dir: &DataDir) -> Option<String> {
+        let (parent, name) = match (dir.parent(), dir.basename()) {
+            (Some(parent), Some(name)) => (parent, name),
+            _ => return None,
+        };
+        let acls = self
+            .0
+            .entry(parent.to_data_uri())
+            .or_insert_with(|| data::get_child_acls(api, &parent).unwrap_or_default());
+        acls.get(&name).map(|read| data::read_acl_name(read))
+    }
+}
+
+fn list_items(dir: &DataDir) -> Vec<DataItem> {
+    dir.list()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap_or_else(|err| quit_err!("Error listing directory: {}", err))
+}
+
+pub(super) enum FileType {
     Image,
     Video,
     Archive,
@@ -153,7 +269,7 @@ enum FileType {
 }
 
 impl FileType {
-    fn from_filename(filename: &str) -> FileType {
+    pub(super) fn from_filename(filename: &str) -> FileType {
         filename
             .rsplit('.')
             .next()
@@ -180,7 +296,7 @@ impl FileType {
         }
     }
 
-    fn to_color(&self) -> Option<Color> {
+    pub(super) fn to_color(&self) -> Option<Color> {
         match *self {
             FileType::Image | FileType::Video => Some(Color::Magenta),
             FileType::Archive => Some(Color::Red),
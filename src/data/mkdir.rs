@@ -1,22 +1,30 @@
 use crate::config::Profile;
+use crate::data::{parse_read_acl, ACL_NAMES};
+use crate::output;
 use crate::CmdRunner;
-use algorithmia::data::{DataAcl, HasDataPath};
+use algorithmia::data::HasDataPath;
 use algorithmia::Algorithmia;
 use docopt::Docopt;
+use rustc_serialize::json::ToJson;
 use std::vec::IntoIter;
 
 static USAGE: &'static str = r##"Usage:
-  mia mkdir <data-dir>
+  mia mkdir [options] <data-dir>
 
   Create an Agorithmia data directory
 
   <data-dir>    Specifies the Algorithmia Data URI
                 The 'data://' prefix is optional
+
+  Options:
+    --acl <acl>     Read permissions: private, my-algos, or public [default: my-algos]
+                      (see 'mia chmod --help' for details)
 "##;
 
 #[derive(RustcDecodable, Debug)]
 struct Args {
     arg_data_dir: String,
+    flag_acl: String,
 }
 
 pub struct MkDir {
@@ -32,7 +40,7 @@ impl CmdRunner for MkDir {
             .and_then(|d| d.argv(argv).decode())
             .unwrap_or_else(|e| e.exit());
 
-        self.create_dir(&*args.arg_data_dir);
+        self.create_dir(&*args.arg_data_dir, &args.flag_acl);
     }
 }
 
@@ -43,10 +51,24 @@ impl MkDir {
         }
     }
 
-    fn create_dir(&self, path: &str) {
+    fn create_dir(&self, path: &str, acl_name: &str) {
+        let acl = parse_read_acl(acl_name).unwrap_or_else(|| {
+            quit_msg!("Invalid ACL '{}'. Expected one of: {}", acl_name, ACL_NAMES)
+        });
+
         let my_dir = self.client.dir(path);
-        match my_dir.create(DataAcl::default()) {
-            Ok(_) => println!("Created directory: {}", my_dir.to_data_uri()),
+        match my_dir.create(acl) {
+            Ok(_) => {
+                let data_uri = my_dir.to_data_uri();
+                output::report(
+                    &format!("Created directory: {}", data_uri),
+                    output::object(vec![
+                        ("data_uri", data_uri.to_json()),
+                        ("acl", acl_name.to_json()),
+                        ("status", "ok".to_json()),
+                    ]),
+                );
+            }
             Err(err) => quit_err!("Error creating directory: {}", err),
         };
     }
@@ -1,16 +1,42 @@
 pub use self::cat::Cat;
+pub use self::chmod::ChMod;
 pub use self::cp::Cp;
+pub use self::du::Du;
+pub use self::find::Find;
 pub use self::ls::Ls;
 pub use self::mkdir::MkDir;
+pub use self::mv::Mv;
 pub use self::rm::Rm;
 pub use self::rmdir::RmDir;
+pub use self::stat::Stat;
+pub use self::sync::DataSync;
+pub use self::tree::Tree;
+
+use crate::api::ApiClient;
+use crate::output;
+use crate::DynError;
+use algorithmia::data::{DataAcl, DataDir, DataFile, DataFileItem, DataItem, HasDataPath, ReadAcl};
+use chrono::{DateTime, Utc};
+use reqwest::header::LAST_MODIFIED;
+use rustc_serialize::json::{Json, ToJson};
+use std::collections::BTreeMap;
+use std::fmt;
 
 mod cat;
+mod chmod;
 mod cp;
+mod du;
+mod find;
 mod ls;
 mod mkdir;
+mod mv;
+mod progress;
 mod rm;
 mod rmdir;
+mod stat;
+mod sync;
+mod tree;
+mod verify;
 
 pub fn size_with_suffix(size: u64) -> String {
     match size / 1024 {
@@ -27,3 +53,255 @@ pub fn size_with_suffix(size: u64) -> String {
         },
     }
 }
+
+// Walks a remote directory tree depth-first, calling `visit` with each item, its depth
+//   (1 for items directly in `dir`), and whether it's the last item in its directory
+pub(crate) fn walk_dir<F>(
+    dir: &DataDir,
+    max_depth: Option<usize>,
+    visit: &mut F,
+) -> Result<(), DynError>
+where
+    F: FnMut(&DataItem, usize, bool),
+{
+    walk_dir_at(dir, 1, max_depth, visit)
+}
+
+fn walk_dir_at<F>(
+    dir: &DataDir,
+    depth: usize,
+    max_depth: Option<usize>,
+    visit: &mut F,
+) -> Result<(), DynError>
+where
+    F: FnMut(&DataItem, usize, bool),
+{
+    let items = dir.list().collect::<Result<Vec<_>, _>>()?;
+    let count = items.len();
+    for (i, item) in items.iter().enumerate() {
+        visit(item, depth, i + 1 == count);
+        if let DataItem::Dir(ref d) = *item {
+            if max_depth.map(|max| depth < max).unwrap_or(true) {
+                walk_dir_at(d, depth + 1, max_depth, visit)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Looks up a file in its directory's listing, e.g. for its modification time
+//   (the `algorithmia` client only sets `last_modified` from a listing)
+pub(crate) fn list_file(file: &DataFile) -> Result<Option<DataFileItem>, DynError> {
+    let (parent, name) = match (file.parent(), file.basename()) {
+        (Some(parent), Some(name)) => (parent, name),
+        _ => return Ok(None),
+    };
+    for entry in parent.list() {
+        if let DataItem::File(f) = entry? {
+            if f.basename().as_ref() == Some(&name) {
+                return Ok(Some(f));
+            }
+        }
+    }
+    Ok(None)
+}
+
+// Parses an HTTP date, e.g. "Wed, 21 Oct 2015 07:28:00 GMT"
+pub(crate) fn parse_http_date(date: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(date)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+// Reads a file's modification time from the Last-Modified header of a HEAD request,
+//   only looking it up in the directory listing if the API doesn't send one
+pub(crate) fn file_modified(
+    api: &ApiClient,
+    file: &DataFile,
+) -> Result<Option<DateTime<Utc>>, DynError> {
+    let url = file.to_url()?;
+    let headers = api.head(url.path())?;
+    let last_modified = headers
+        .get(LAST_MODIFIED)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_http_date);
+    match last_modified {
+        Some(modified) => Ok(Some(modified)),
+        None => Ok(list_file(file)?.map(|f| f.last_modified)),
+    }
+}
+
+// Structured output for a listed file or directory
+pub(crate) fn item_record(item: &DataItem) -> Json {
+    match *item {
+        DataItem::Dir(ref d) => output::object(vec![
+            ("data_uri", d.to_data_uri().to_json()),
+            ("name", d.basename().to_json()),
+            ("type", "dir".to_json()),
+        ]),
+        DataItem::File(ref f) => output::object(vec![
+            ("data_uri", f.to_data_uri().to_json()),
+            ("name", f.basename().to_json()),
+            ("type", "file".to_json()),
+            ("size", f.size.to_json()),
+            ("last_modified", f.last_modified.to_rfc3339().to_json()),
+        ]),
+    }
+}
+
+// Counts of the items in a recursive listing
+#[derive(Default)]
+pub(crate) struct Summary {
+    pub dirs: u64,
+    pub files: u64,
+    pub bytes: u64,
+}
+
+impl Summary {
+    pub fn add(&mut self, item: &DataItem) {
+        match *item {
+            DataItem::Dir(_) => self.dirs += 1,
+            DataItem::File(ref f) => {
+                self.files += 1;
+                self.bytes += f.size;
+            }
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {}, {} {}, {}B total",
+            self.dirs,
+            if self.dirs == 1 {
+                "directory"
+            } else {
+                "directories"
+            },
+            self.files,
+            if self.files == 1 { "file" } else { "files" },
+            size_with_suffix(self.bytes)
+        )
+    }
+}
+
+// True for Algorithmia Data URIs, including connectors (e.g. s3://), but not file:// paths
+pub(crate) fn is_remote(path: &str) -> bool {
+    let parts: Vec<_> = path.splitn(2, "://").collect();
+    parts.len() == 2 && parts[0] != "file"
+}
+
+// Read ACL names accepted by `chmod` and `mkdir --acl`
+pub(crate) static ACL_NAMES: &str = "private, public, my-algos";
+
+pub(crate) fn parse_read_acl(name: &str) -> Option<ReadAcl> {
+    match name {
+        "private" => Some(ReadAcl::Private),
+        "public" => Some(ReadAcl::Public),
+        "my-algos" | "my-algorithms" => Some(ReadAcl::MyAlgorithms),
+        _ => None,
+    }
+}
+
+// Display name for the read ACL of a directory (the inverse of `parse_read_acl`)
+pub(crate) fn read_acl_name(read: &[String]) -> String {
+    let public = DataAcl::from(ReadAcl::Public).read;
+    if read.is_empty() {
+        "private".to_string()
+    } else if read.iter().any(|r| public.contains(r)) {
+        "public".to_string()
+    } else if *read == *DataAcl::from(ReadAcl::MyAlgorithms).read {
+        "my-algos".to_string()
+    } else {
+        read.join(",")
+    }
+}
+
+// The `algorithmia` client can set an ACL on create, but can't read or update one
+pub(crate) fn get_dir_acl(api: &ApiClient, dir: &DataDir) -> Result<Vec<String>, DynError> {
+    let url = dir.to_url()?;
+    let res = api.get(&format!("{}?acl=true", url.path()))?;
+    Ok(acl_read(&res))
+}
+
+// Read ACLs of a directory's subdirectories, by name, which its listing includes with
+//   `acl=true` (so that `ls -l` needs one request per page rather than one per directory)
+pub(crate) fn get_child_acls(
+    api: &ApiClient,
+    dir: &DataDir,
+) -> Result<BTreeMap<String, Vec<String>>, DynError> {
+    let mut url = dir.to_url()?;
+    let mut acls = BTreeMap::new();
+    let mut marker: Option<String> = None;
+    loop {
+        url.query_pairs_mut().clear().append_pair("acl", "true");
+        if let Some(ref marker) = marker {
+            url.query_pairs_mut().append_pair("marker", marker);
+        }
+        let res = api.get(&format!("{}?{}", url.path(), url.query().unwrap_or("")))?;
+        let folders = res.find("folders").and_then(Json::as_array);
+        for folder in folders.into_iter().flatten() {
+            let name = folder.find("name").and_then(Json::as_string);
+            if let (Some(name), Some(_)) = (name, folder.find("acl")) {
+                acls.insert(name.to_owned(), acl_read(folder));
+            }
+        }
+        marker = res
+            .find("marker")
+            .and_then(Json::as_string)
+            .map(String::from);
+        if marker.is_none() {
+            return Ok(acls);
+        }
+    }
+}
+
+fn acl_read(json: &Json) -> Vec<String> {
+    json.find_path(&["acl", "read"])
+        .and_then(Json::as_array)
+        .map(|read| {
+            read.iter()
+                .filter_map(Json::as_string)
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub(crate) fn set_dir_acl(api: &ApiClient, dir: &DataDir, acl: ReadAcl) -> Result<(), DynError> {
+    let url = dir.to_url()?;
+    let read = DataAcl::from(acl)
+        .read
+        .into_iter()
+        .map(Json::String)
+        .collect();
+    let mut acl = BTreeMap::new();
+    acl.insert("read".to_string(), Json::Array(read));
+    let mut body = BTreeMap::new();
+    body.insert("acl".to_string(), Json::Object(acl));
+    api.patch(url.path(), &Json::Object(body))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_remote() {
+        assert!(is_remote("data://.my/foo"));
+        assert!(is_remote("s3://bucket/foo"));
+        assert!(!is_remote("file://foo"));
+        assert!(!is_remote("./foo"));
+    }
+
+    #[test]
+    fn test_parse_http_date() {
+        let modified = parse_http_date("Wed, 21 Oct 2015 07:28:00 GMT").unwrap();
+        assert_eq!(modified.timestamp(), 1445412480);
+        assert_eq!(modified.to_rfc3339(), "2015-10-21T07:28:00+00:00");
+        assert!(parse_http_date("2015-10-21T07:28:00Z").is_none());
+    }
+}
@@ -0,0 +1,254 @@
+use super::cp::{copy_remote_file, remote_dest_dir, remote_dest_file};
+use super::{get_dir_acl, parse_read_acl, read_acl_name};
+use crate::api::ApiClient;
+use crate::config::Profile;
+use crate::output::{self, Records};
+use crate::CmdRunner;
+use algorithmia::data::{DataAcl, DataDir, DataFile, DataFileItem, DataItem, HasDataPath};
+use algorithmia::Algorithmia;
+use docopt::Docopt;
+use rustc_serialize::json::ToJson;
+use std::vec::IntoIter;
+
+static USAGE: &str = r##"Usage:
+  mia mv <source>... <dest>
+  mia move <source>... <dest>
+
+  Move or rename files and directories in the Algorithmia Data API
+
+  <source>, <dest>  Specifies the Algorithmia Data URIs
+                    The 'data://' prefix is optional
+
+  The Data API has no native move, so files are copied to the destination,
+  and each source is only deleted once its copy is verified. Files added to
+  a source directory during the move are kept, along with their directories.
+
+  Examples:
+    mia mv .my/foo/a.jpg .my/foo/b.jpg                   Rename a file
+    mia mv .my/foo/a.jpg .my/foo/b.jpg .my/bar           Move 2 files into the 'bar' directory
+    mia mv .my/foo .my/bar                               Move 'foo' into 'bar' if it exists,
+                                                           otherwise rename 'foo' to 'bar'
+"##;
+
+#[derive(RustcDecodable, Debug)]
+struct Args {
+    arg_source: Vec<String>,
+    arg_dest: String,
+}
+
+// Files and directories of a tree that were copied, with each directory after its children
+#[derive(Default)]
+struct Copied {
+    files: Vec<DataFileItem>,
+    dirs: Vec<DataDir>,
+}
+
+pub struct Mv {
+    client: Algorithmia,
+    api: ApiClient,
+    records: Records,
+}
+impl CmdRunner for Mv {
+    fn get_usage() -> &'static str {
+        USAGE
+    }
+
+    fn cmd_main(&self, argv: IntoIter<String>) {
+        let args: Args = Docopt::new(USAGE)
+            .and_then(|d| d.argv(argv).decode())
+            .unwrap_or_else(|e| e.exit());
+
+        // Like `mv`, multiple sources can only be moved into a directory
+        if args.arg_source.len() > 1 {
+            match self.client.data(&args.arg_dest).into_type() {
+                Ok(DataItem::Dir(_)) => (),
+                _ => quit_msg!("Target {} is not a directory", args.arg_dest),
+            }
+        }
+
+        let mut failed = false;
+        for source in &args.arg_source {
+            if let Err(err) = self.move_path(source, &args.arg_dest) {
+                eprintln_red!("{}", err);
+                self.records.add(output::object(vec![
+                    ("source", source.to_json()),
+                    ("status", "error".to_json()),
+                    ("error", err.to_json()),
+                ]));
+                failed = true;
+            }
+        }
+        self.records.finish();
+
+        if failed {
+            ::std::process::exit(1);
+        }
+    }
+}
+
+impl Mv {
+    pub fn new(profile: Profile) -> Self {
+        Mv {
+            client: profile.client(),
+            api: profile.api_client(),
+            records: Records::new(),
+        }
+    }
+
+    fn move_path(&self, source: &str, dest: &str) -> Result<(), String> {
+        match self.client.data(source).into_type() {
+            Ok(DataItem::File(f)) => {
+                let dest_file = remote_dest_file(&self.client, dest, &f.basename().unwrap());
+                if dest_file.to_data_uri() == f.to_data_uri() {
+                    return Err(format!("{} and {} are the same file", source, dest));
+                }
+                self.move_file(&f, &dest_file)?;
+                let (src_uri, dest_uri) = (f.to_data_uri(), dest_file.to_data_uri());
+                let text = format!("Moved {} to {}", src_uri, dest_uri);
+                self.report_moved(&text, &src_uri, &dest_uri, 1);
+                Ok(())
+            }
+            Ok(DataItem::Dir(d)) => {
+                let dest_dir = remote_dest_dir(&self.client, dest, source, d.basename());
+                let (src_uri, dest_uri) = (d.to_data_uri(), dest_dir.to_data_uri());
+                if dest_uri == src_uri || dest_uri.starts_with(&format!("{}/", src_uri)) {
+                    return Err(format!("Cannot move {} into itself", src_uri));
+                }
+
+                let mut copied = Copied::default();
+                self.copy_dir(&d, &dest_dir, &mut copied)?;
+                let count = copied.files.len() as u64;
+                if !self.remove_copied(&copied)? {
+                    return Err(format!(
+                        "Moved {} files to {}, but kept {} since files were added to it during the move",
+                        count, dest_uri, src_uri
+                    ));
+                }
+                let text = format!("Moved {} to {} ({} files)", src_uri, dest_uri, count);
+                self.report_moved(&text, &src_uri, &dest_uri, count);
+                Ok(())
+            }
+            Err(err) => Err(format!("Error moving {}: {}", source, err)),
+        }
+    }
+
+    fn report_moved(&self, text: &str, source: &str, dest: &str, files: u64) {
+        self.records.report(
+            text,
+            output::object(vec![
+                ("source", source.to_json()),
+                ("dest", dest.to_json()),
+                ("files", files.to_json()),
+                ("status", "ok".to_json()),
+            ]),
+        );
+    }
+
+    fn move_file(&self, source: &DataFileItem, dest: &DataFile) -> Result<(), String> {
+        copy_remote_file(&self.client, source, dest, None)?;
+        self.verify(dest, source.size)?;
+        source
+            .delete()
+            .map_err(|err| format!("Error deleting file {}: {}", source.to_data_uri(), err))
+    }
+
+    // Copies a directory tree (keeping the read ACL of each directory), recording each file
+    //   once its copy is verified. Sources are left in place so that nothing is deleted unless
+    //   the whole tree was copied.
+    fn copy_dir(
+        &self,
+        source: &DataDir,
+        dest: &DataDir,
+        copied: &mut Copied,
+    ) -> Result<(), String> {
+        self.create_dir_like(source, dest)?;
+
+        for entry in source.list() {
+            match entry {
+                Ok(DataItem::Dir(d)) => {
+                    let child = dest.child(&d.basename().unwrap());
+                    self.copy_dir(&d, &child, copied)?;
+                }
+                Ok(DataItem::File(f)) => {
+                    let child = dest.child(&f.basename().unwrap());
+                    copy_remote_file(&self.client, &f, &child, None)?;
+                    self.verify(&child, f.size)?;
+                    copied.files.push(f);
+                }
+                Err(err) => {
+                    return Err(format!(
+                        "Error listing directory {}: {}",
+                        source.to_data_uri(),
+                        err
+                    ))
+                }
+            }
+        }
+        copied.dirs.push(self.client.dir(&source.to_data_uri()));
+        Ok(())
+    }
+
+    // Deletes the copied files, then each directory left empty, returning whether the whole
+    //   source tree was removed. Files written to the source during the move are kept.
+    fn remove_copied(&self, copied: &Copied) -> Result<bool, String> {
+        for f in &copied.files {
+            f.delete()
+                .map_err(|err| format!("Error deleting file {}: {}", f.to_data_uri(), err))?;
+        }
+
+        let mut removed = true;
+        for d in &copied.dirs {
+            match d.list().next() {
+                None => {
+                    d.delete(false).map_err(|err| {
+                        format!("Error deleting directory {}: {}", d.to_data_uri(), err)
+                    })?;
+                }
+                Some(Ok(_)) => removed = false,
+                Some(Err(err)) => {
+                    return Err(format!(
+                        "Error listing directory {}: {}",
+                        d.to_data_uri(),
+                        err
+                    ))
+                }
+            }
+        }
+        Ok(removed)
+    }
+
+    fn create_dir_like(&self, source: &DataDir, dest: &DataDir) -> Result<(), String> {
+        if let Ok(true) = dest.exists() {
+            return Ok(());
+        }
+        let acl = get_dir_acl(&self.api, source)
+            .ok()
+            .and_then(|read| parse_read_acl(&read_acl_name(&read)))
+            .map(DataAcl::from)
+            .unwrap_or_default();
+        dest.create(acl)
+            .map_err(|err| format!("Error creating directory {}: {}", dest.to_data_uri(), err))
+    }
+
+    // Confirms that the copy exists with the size of its source before the source is deleted
+    fn verify(&self, dest: &DataFile, size: u64) -> Result<(), String> {
+        match self.client.data(&dest.to_data_uri()).into_type() {
+            Ok(DataItem::File(ref f)) if f.size == size => Ok(()),
+            Ok(DataItem::File(f)) => Err(format!(
+                "Copy of {} has {} bytes (expected {}), not deleting the source",
+                dest.to_data_uri(),
+                f.size,
+                size
+            )),
+            Ok(DataItem::Dir(_)) => Err(format!(
+                "Copy of {} is a directory, not deleting the source",
+                dest.to_data_uri()
+            )),
+            Err(err) => Err(format!(
+                "Error verifying {}: {}, not deleting the source",
+                dest.to_data_uri(),
+                err
+            )),
+        }
+    }
+}
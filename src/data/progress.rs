@@ -0,0 +1,339 @@
+use super::size_with_suffix;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use terminal_size::{terminal_size, Width};
+
+// How often the display is redrawn on a terminal, or a status line is printed otherwise
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+const STATUS_INTERVAL: Duration = Duration::from_secs(5);
+const BAR_WIDTH: usize = 20;
+
+// Live progress of concurrent transfers, drawn on stderr below any other output
+//   When stderr isn't a terminal, a plain status line is printed periodically instead
+pub(super) struct Progress {
+    tty: bool,
+    state: Mutex<State>,
+}
+
+struct State {
+    started: Instant,
+    last_update: Instant,
+    files_total: u32,
+    files_done: u32,
+    bytes_total: u64,
+    bytes_done: u64,
+    next_id: usize,
+    active: Vec<Active>,
+    lines_drawn: usize,
+}
+
+// A file that is currently being transferred
+struct Active {
+    id: usize,
+    name: String,
+    size: Option<u64>,
+    done: u64,
+}
+
+impl Progress {
+    pub fn new() -> Arc<Progress> {
+        let now = Instant::now();
+        Arc::new(Progress {
+            tty: crate::is_stderr_tty(),
+            state: Mutex::new(State {
+                started: now,
+                last_update: now,
+                files_total: 0,
+                files_done: 0,
+                bytes_total: 0,
+                bytes_done: 0,
+                next_id: 0,
+                active: Vec::new(),
+                lines_drawn: 0,
+            }),
+        })
+    }
+
+    // Adds a file to the total, including its size if it's already known
+    pub fn queue(&self, size: Option<u64>) {
+        let mut state = self.state.lock().unwrap();
+        state.files_total += 1;
+        state.bytes_total += size.unwrap_or(0);
+    }
+
+    // Counts a file as transferred
+    pub fn complete(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.files_done += 1;
+        self.update(&mut state);
+    }
+
+    // Clears the display while printing other output (e.g. the result of a transfer)
+    //   so that it isn't interleaved with the progress lines
+    pub fn suspend<F: FnOnce()>(&self, print: F) {
+        let mut state = self.state.lock().unwrap();
+        self.clear(&mut state);
+        print();
+    }
+
+    // Clears the display, which is only redrawn by further progress
+    pub fn finish(&self) {
+        let mut state = self.state.lock().unwrap();
+        self.clear(&mut state);
+    }
+
+    // Total bytes transferred, elapsed time, and throughput, e.g. "12.0MB in 3s (4.0MB/s)"
+    pub fn summary(&self) -> String {
+        let state = self.state.lock().unwrap();
+        let elapsed = state.started.elapsed();
+        format!(
+            "{}B in {} ({}B/s)",
+            size_with_suffix(state.bytes_done),
+            format_duration(elapsed.as_secs()),
+            size_with_suffix(state.rate() as u64)
+        )
+    }
+
+    fn update(&self, state: &mut State) {
+        let interval = if self.tty {
+            REDRAW_INTERVAL
+        } else {
+            STATUS_INTERVAL
+        };
+        let now = Instant::now();
+        if now.duration_since(state.last_update) < interval {
+            return;
+        }
+        state.last_update = now;
+
+        if self.tty {
+            self.redraw(state);
+        } else {
+            eprintln!("{}", state.status());
+        }
+    }
+
+    fn redraw(&self, state: &mut State) {
+        let width = match terminal_size() {
+            Some((Width(w), _)) => w as usize,
+            None => 80,
+        };
+        let mut lines: Vec<String> = state.active.iter().map(|a| a.line(width)).collect();
+        lines.push(state.status());
+
+        // Lines must not wrap, or moving the cursor back up wouldn't reach the first line
+        let mut out = String::new();
+        if state.lines_drawn > 0 {
+            out.push_str(&format!("\x1b[{}A\x1b[J", state.lines_drawn));
+        }
+        for line in &lines {
+            out.extend(line.chars().take(width.saturating_sub(1)));
+            out.push('\n');
+        }
+        let stderr = io::stderr();
+        let mut err = stderr.lock();
+        let _ = err.write_all(out.as_bytes());
+        let _ = err.flush();
+        state.lines_drawn = lines.len();
+    }
+
+    fn clear(&self, state: &mut State) {
+        if state.lines_drawn > 0 {
+            eprint!("\x1b[{}A\x1b[J", state.lines_drawn);
+            state.lines_drawn = 0;
+        }
+    }
+}
+
+impl State {
+    // Average throughput in bytes per second
+    fn rate(&self) -> f64 {
+        let elapsed = self.started.elapsed();
+        let secs = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_millis()) / 1000.0;
+        if secs > 0.0 {
+            self.bytes_done as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    // e.g. "3/10 files, 1.2GB of 4.0GB, 12.0MB/s, ETA 3m52s"
+    //   Sizes of files that haven't started are only known when they were listed before queueing
+    fn status(&self) -> String {
+        let rate = self.rate();
+        let eta = if rate > 0.0 && self.bytes_total >= self.bytes_done {
+            let remaining = (self.bytes_total - self.bytes_done) as f64 / rate;
+            format_duration(remaining.ceil() as u64)
+        } else {
+            "-".to_string()
+        };
+        format!(
+            "{}/{} files, {}B of {}B, {}B/s, ETA {}",
+            self.files_done,
+            self.files_total,
+            size_with_suffix(self.bytes_done),
+            size_with_suffix(self.bytes_total),
+            size_with_suffix(rate as u64),
+            eta
+        )
+    }
+}
+
+impl Active {
+    // e.g. "data://.my/foo/big.bin  [========>           ]  45% 1.2GB/2.6GB"
+    fn line(&self, width: usize) -> String {
+        let detail = match self.size {
+            Some(size) if size > 0 => {
+                let done = self.done.min(size);
+                let filled = (done as f64 / size as f64 * BAR_WIDTH as f64) as usize;
+                let mut bar = "=".repeat(filled);
+                if filled < BAR_WIDTH {
+                    bar.push('>');
+                }
+                format!(
+                    "[{:<bar_width$}] {:>3}% {}B/{}B",
+                    bar,
+                    done * 100 / size,
+                    size_with_suffix(self.done),
+                    size_with_suffix(size),
+                    bar_width = BAR_WIDTH
+                )
+            }
+            _ => format!("{}B", size_with_suffix(self.done)),
+        };
+        // Room for the bar, percentage, and sizes (e.g. "1023.9M"), so that bars line up
+        let detail_width = BAR_WIDTH + 24;
+        let name_width = width.saturating_sub(detail_width + 2).max(10);
+        format!(
+            "{:<width$} {}",
+            truncate_start(&self.name, name_width),
+            detail,
+            width = name_width
+        )
+    }
+}
+
+// Shortens a name to `width` characters, keeping the end (e.g. the file name of a long path)
+fn truncate_start(name: &str, width: usize) -> String {
+    let len = name.chars().count();
+    if len <= width {
+        return name.to_string();
+    }
+    let keep = width.saturating_sub(3);
+    let tail: String = name.chars().skip(len - keep).collect();
+    format!("...{}", tail)
+}
+
+fn format_duration(secs: u64) -> String {
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m{:02}s", m, s),
+        (h, m, s) => format!("{}h{:02}m{:02}s", h, m, s),
+    }
+}
+
+// A file being transferred, which stops being shown when dropped
+pub(super) struct Transfer {
+    progress: Arc<Progress>,
+    id: usize,
+    // Whether the size still has to be added to the total
+    size_unknown: bool,
+}
+
+impl Transfer {
+    // Starts showing a file, whose size is already in the total if it was known when queued
+    pub fn start(progress: &Arc<Progress>, name: &str, queued_size: Option<u64>) -> Transfer {
+        let mut state = progress.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.active.push(Active {
+            id,
+            name: name.to_string(),
+            size: queued_size,
+            done: 0,
+        });
+        progress.update(&mut state);
+        Transfer {
+            progress: progress.clone(),
+            id,
+            size_unknown: queued_size.is_none(),
+        }
+    }
+
+    // Sets the size once it's known (e.g. from the response of a download)
+    pub fn set_size(&mut self, size: u64) {
+        let mut state = self.progress.state.lock().unwrap();
+        if self.size_unknown {
+            state.bytes_total += size;
+            self.size_unknown = false;
+        }
+        if let Some(active) = state.active.iter_mut().find(|a| a.id == self.id) {
+            active.size = Some(size);
+        }
+    }
+
+    // Restarts the count of bytes transferred, e.g. when resuming a download after an error
+    pub fn restart_at(&self, done: u64) {
+        let mut state = self.progress.state.lock().unwrap();
+        let previous = match state.active.iter_mut().find(|a| a.id == self.id) {
+            Some(active) => std::mem::replace(&mut active.done, done),
+            None => return,
+        };
+        state.bytes_done = state.bytes_done - previous + done;
+    }
+
+    pub fn add(&self, bytes: u64) {
+        let mut state = self.progress.state.lock().unwrap();
+        state.bytes_done += bytes;
+        if let Some(active) = state.active.iter_mut().find(|a| a.id == self.id) {
+            active.done += bytes;
+        }
+        self.progress.update(&mut state);
+    }
+}
+
+impl Drop for Transfer {
+    fn drop(&mut self) {
+        let mut state = self.progress.state.lock().unwrap();
+        state.active.retain(|a| a.id != self.id);
+    }
+}
+
+// Reports the bytes read from `inner` to a transfer
+pub(super) struct ProgressReader<R> {
+    inner: R,
+    transfer: Transfer,
+}
+
+impl<R> ProgressReader<R> {
+    pub fn new(inner: R, transfer: Transfer) -> ProgressReader<R> {
+        ProgressReader { inner, transfer }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.transfer.add(n as u64);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(5), "5s");
+        assert_eq!(format_duration(125), "2m05s");
+        assert_eq!(format_duration(3 * 3600 + 61), "3h01m01s");
+    }
+
+    #[test]
+    fn test_truncate_start() {
+        assert_eq!(truncate_start("data://.my/a.txt", 20), "data://.my/a.txt");
+        assert_eq!(truncate_start("data://.my/foo/bar.txt", 12), "...o/bar.txt");
+    }
+}
@@ -1,26 +1,69 @@
 use crate::config::Profile;
+use crate::output::{self, Records};
 use crate::CmdRunner;
-use algorithmia::data::HasDataPath;
+use algorithmia::data::{DataItem, HasDataPath};
 use algorithmia::Algorithmia;
 use docopt::Docopt;
+use glob::Pattern;
+use rustc_serialize::json::ToJson;
+use std::io::{self, BufRead, Write};
 use std::vec::IntoIter;
 
 static USAGE: &'static str = r##"Usage:
-  mia rm <data-file>
+  mia rm [options] <data-path>...
 
-  Removes a file from the Agorithmia Data API
+  Removes files (and optionally directories) from the Agorithmia Data API
 
-  <data-file>       Specifies the Algorithmia Data URI
+  <data-path>       Specifies the Algorithmia Data URI
                     The 'data://' prefix is optional
+                    The last path segment may be a glob pattern (e.g. data://.my/imgs/*.tmp),
+                    unless a file or directory with that exact name exists
+
+  Options:
+    -r, --recursive     Remove directories and their contents
+    -i, --interactive   Prompt before every removal
+    -y, --yes           Don't prompt before removing files matched by a glob or
+                          directories removed with -r
+
+  Examples:
+    mia rm .my/imgs/a.png .my/imgs/b.png                 Remove several files
+    mia rm 'data://.my/imgs/*.tmp'                       Remove files matching a pattern
+    mia rm -r --yes .my/scratch                          Remove a directory without prompting
 "##;
 
 #[derive(RustcDecodable, Debug)]
 struct Args {
-    arg_data_file: String,
+    arg_data_path: Vec<String>,
+    flag_recursive: bool,
+    flag_interactive: bool,
+    flag_yes: bool,
+}
+
+// Data URI of a file or directory to remove
+#[derive(Debug, PartialEq)]
+enum Target {
+    File(String),
+    Dir(String),
+}
+
+impl Target {
+    fn data_uri(&self) -> &str {
+        match *self {
+            Target::File(ref uri) | Target::Dir(ref uri) => uri,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match *self {
+            Target::File(_) => "file",
+            Target::Dir(_) => "dir",
+        }
+    }
 }
 
 pub struct Rm {
     client: Algorithmia,
+    records: Records,
 }
 impl CmdRunner for Rm {
     fn get_usage() -> &'static str {
@@ -32,7 +75,85 @@ impl CmdRunner for Rm {
             .and_then(|d| d.argv(argv).decode())
             .unwrap_or_else(|e| e.exit());
 
-        self.delete_file(&*args.arg_data_file);
+        let mut failed = false;
+        let mut expanded = false;
+        let mut files = Vec::new();
+        let mut dirs = Vec::new();
+        for path in &args.arg_data_path {
+            let resolved = resolve_path(
+                path,
+                |path| self.resolve(path),
+                |parent, pattern| self.expand_glob(parent, pattern),
+            );
+            match resolved {
+                Ok((ref t, _)) if t.is_empty() => {
+                    eprintln_red!("No matches for {}", path);
+                    failed = true;
+                }
+                Ok((t, is_glob)) => {
+                    expanded |= is_glob;
+                    for target in t {
+                        match target {
+                            Target::File(uri) => files.push(uri),
+                            Target::Dir(uri) => dirs.push(uri),
+                        }
+                    }
+                }
+                Err(err) => {
+                    eprintln_red!("{}", err);
+                    failed = true;
+                }
+            }
+        }
+
+        // Directories require -r
+        if !args.flag_recursive {
+            for dir in dirs.drain(..) {
+                eprintln_red!("Skipping directory {} (use -r to remove directories)", dir);
+                failed = true;
+            }
+        }
+
+        // Confirm once for deletes that may be broader than intended
+        let count = files.len() + dirs.len();
+        if !args.flag_interactive && !args.flag_yes && count > 0 && (expanded || !dirs.is_empty()) {
+            let question = match dirs.len() {
+                0 => format!("Remove {} file(s)?", files.len()),
+                n => format!(
+                    "Remove {} file(s) and {} directory(s) with their contents?",
+                    files.len(),
+                    n
+                ),
+            };
+            if !confirm(&question) {
+                quit_msg!("Nothing removed");
+            }
+        }
+
+        let targets = files
+            .into_iter()
+            .map(Target::File)
+            .chain(dirs.into_iter().map(Target::Dir));
+        for target in targets {
+            if args.flag_interactive && !confirm(&format!("Remove {}?", target.data_uri())) {
+                continue;
+            }
+            if let Err(err) = self.delete(&target) {
+                eprintln_red!("{}", err);
+                self.records.add(output::object(vec![
+                    ("data_uri", target.data_uri().to_json()),
+                    ("type", target.kind().to_json()),
+                    ("status", "error".to_json()),
+                    ("error", err.to_json()),
+                ]));
+                failed = true;
+            }
+        }
+        self.records.finish();
+
+        if failed {
+            ::std::process::exit(1);
+        }
     }
 }
 
@@ -40,14 +161,183 @@ impl Rm {
     pub fn new(profile: Profile) -> Self {
         Rm {
             client: profile.client(),
+            records: Records::new(),
+        }
+    }
+
+    fn resolve(&self, path: &str) -> Result<Target, String> {
+        match self.client.data(path).into_type() {
+            Ok(DataItem::File(f)) => Ok(Target::File(f.to_data_uri())),
+            Ok(DataItem::Dir(d)) => Ok(Target::Dir(d.to_data_uri())),
+            Err(err) => Err(format!("Error removing {}: {}", path, err)),
+        }
+    }
+
+    fn expand_glob(&self, parent: &str, pattern: &Pattern) -> Result<Vec<Target>, String> {
+        let mut targets = Vec::new();
+        for entry in self.client.dir(parent).list() {
+            let (name, target) = match entry {
+                Ok(DataItem::File(f)) => (f.basename(), Target::File(f.to_data_uri())),
+                Ok(DataItem::Dir(d)) => (d.basename(), Target::Dir(d.to_data_uri())),
+                Err(err) => return Err(format!("Error listing {}: {}", parent, err)),
+            };
+            match name {
+                Some(ref n) if pattern.matches(n) => targets.push(target),
+                _ => (),
+            }
         }
+        Ok(targets)
     }
 
-    fn delete_file(&self, path: &str) {
-        let my_file = self.client.file(path);
-        match my_file.delete() {
-            Ok(_) => println!("Deleted file {}", my_file.to_data_uri()),
-            Err(err) => quit_err!("Error deleting file: {}", err),
+    fn report_deleted(&self, text: &str, target: &Target, files: u64) {
+        self.records.report(
+            text,
+            output::object(vec![
+                ("data_uri", target.data_uri().to_json()),
+                ("type", target.kind().to_json()),
+                ("deleted", files.to_json()),
+                ("status", "ok".to_json()),
+            ]),
+        );
+    }
+
+    fn delete(&self, target: &Target) -> Result<(), String> {
+        match *target {
+            Target::File(ref uri) => match self.client.file(uri).delete() {
+                Ok(_) => {
+                    self.report_deleted(&format!("Deleted file {}", uri), target, 1);
+                    Ok(())
+                }
+                Err(err) => Err(format!("Error deleting file {}: {}", uri, err)),
+            },
+            Target::Dir(ref uri) => match self.client.dir(uri).delete(true) {
+                Ok(res) => {
+                    let text = format!("Deleted directory {} ({} files)", uri, res.deleted);
+                    self.report_deleted(&text, target, res.deleted);
+                    Ok(())
+                }
+                Err(err) => Err(format!("Error deleting directory {}: {}", uri, err)),
+            },
+        }
+    }
+}
+
+// Resolves a path to remove, and whether it was expanded as a glob pattern
+//   A path that exists is removed as is, even if its name looks like a pattern (e.g. report[1].csv)
+fn resolve_path<R, E>(path: &str, resolve: R, expand: E) -> Result<(Vec<Target>, bool), String>
+where
+    R: Fn(&str) -> Result<Target, String>,
+    E: Fn(&str, &Pattern) -> Result<Vec<Target>, String>,
+{
+    match (resolve(path), glob_pattern(path)) {
+        (Ok(target), _) => Ok((vec![target], false)),
+        (Err(err), Ok(None)) => Err(err),
+        (Err(_), Ok(Some((parent, pattern)))) => expand(parent, &pattern).map(|t| (t, true)),
+        (Err(_), Err(err)) => Err(err),
+    }
+}
+
+// Splits a path whose last segment is a glob pattern into the parent path and pattern
+//   A pattern directly under the root (e.g. data://*.tmp) keeps the 'scheme://' as its parent
+fn glob_pattern(path: &str) -> Result<Option<(&str, Pattern)>, String> {
+    let root_len = path.find("://").map_or(0, |i| i + 3);
+    let (parent, basename) = match path[root_len..].rfind('/') {
+        Some(i) => (&path[..root_len + i], &path[root_len + i + 1..]),
+        None if root_len > 0 => (&path[..root_len], &path[root_len..]),
+        None => ("", path),
+    };
+    if !basename.contains(&['*', '?', '['][..]) {
+        return Ok(None);
+    }
+    if parent.is_empty() {
+        return Err(format!(
+            "Cannot expand {}: patterns need a directory (e.g. data://.my/{0})",
+            path
+        ));
+    }
+    match Pattern::new(basename) {
+        Ok(pattern) => Ok(Some((parent, pattern))),
+        Err(err) => Err(format!("Invalid pattern '{}': {}", basename, err)),
+    }
+}
+
+// Prompts on stderr, treating anything but 'y' or 'yes' (including EOF) as no
+fn confirm(question: &str) -> bool {
+    eprint!("{} [y/N] ", question);
+    let _ = io::stderr().flush();
+
+    let mut line = String::new();
+    match io::stdin().lock().read_line(&mut line) {
+        Ok(_) => {
+            let answer = line.trim().to_lowercase();
+            answer == "y" || answer == "yes"
+        }
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_glob_pattern() {
+        let split = |path| {
+            glob_pattern(path)
+                .unwrap()
+                .map(|(parent, p)| (parent, p.as_str().to_owned()))
         };
+        assert_eq!(
+            split("data://.my/foo/*.tmp"),
+            Some(("data://.my/foo", "*.tmp".into()))
+        );
+        assert_eq!(split("data://*.tmp"), Some(("data://", "*.tmp".into())));
+        assert_eq!(split("s3://*.csv"), Some(("s3://", "*.csv".into())));
+        assert_eq!(
+            split(".my/foo/file?.txt"),
+            Some((".my/foo", "file?.txt".into()))
+        );
+        assert_eq!(split("data://.my/foo/bar.txt"), None);
+        assert_eq!(split("bar.txt"), None);
+        assert!(glob_pattern("*.tmp").is_err());
+        assert!(glob_pattern(".my/foo/a[b.txt").is_err());
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        // A fake directory data://.my/foo with report[1].csv, report1.csv, and a[b.txt
+        let files = &["report[1].csv", "report1.csv", "a[b.txt"];
+        let uri = |name: &str| format!("data://.my/foo/{}", name);
+        let resolve = |path: &str| match files.iter().find(|&&name| path == uri(name)) {
+            Some(_) => Ok(Target::File(path.to_owned())),
+            None => Err(format!("Error removing {}: not found", path)),
+        };
+        let expand = |_: &str, pattern: &Pattern| {
+            let matched = files.iter().filter(|&&name| pattern.matches(name));
+            Ok(matched.map(|&name| Target::File(uri(name))).collect())
+        };
+        let resolve_path = |path: &str| resolve_path(path, resolve, expand);
+
+        // Names with pattern characters are removed literally when they exist
+        let literal = uri("report[1].csv");
+        assert_eq!(
+            resolve_path(&literal),
+            Ok((vec![Target::File(literal.clone())], false))
+        );
+        let literal = uri("a[b.txt");
+        assert_eq!(
+            resolve_path(&literal),
+            Ok((vec![Target::File(literal.clone())], false))
+        );
+
+        // Otherwise they're expanded
+        assert_eq!(
+            resolve_path(&uri("report[0-9].csv")),
+            Ok((vec![Target::File(uri("report1.csv"))], true))
+        );
+        assert!(resolve_path(&uri("b[c.txt")).is_err());
+        assert!(resolve_path("*.csv")
+            .unwrap_err()
+            .contains("need a directory"));
     }
 }
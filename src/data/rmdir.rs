@@ -1,8 +1,10 @@
 use crate::config::Profile;
+use crate::output;
 use crate::CmdRunner;
 use algorithmia::data::HasDataPath;
 use algorithmia::Algorithmia;
 use docopt::Docopt;
+use rustc_serialize::json::ToJson;
 use std::vec::IntoIter;
 
 static USAGE: &'static str = r##"Usage:
@@ -51,7 +53,18 @@ impl RmDir {
     fn delete_dir(&self, path: &str, force: bool) {
         let my_dir = self.client.dir(path);
         match my_dir.delete(force) {
-            Ok(_) => println!("Deleted directory {}", my_dir.to_data_uri()),
+            Ok(res) => {
+                let data_uri = my_dir.to_data_uri();
+                output::report(
+                    &format!("Deleted directory {}", data_uri),
+                    output::object(vec![
+                        ("data_uri", data_uri.to_json()),
+                        ("type", "dir".to_json()),
+                        ("deleted", res.deleted.to_json()),
+                        ("status", "ok".to_json()),
+                    ]),
+                );
+            }
             Err(err) => quit_err!("Error deleting directory: {}", err),
         };
     }
@@ -0,0 +1,275 @@
+use crate::api::ApiClient;
+use crate::config::Profile;
+use crate::output::{self, Records};
+use crate::{data, CmdRunner};
+use algorithmia::data::{DataDir, DataFile, HasDataPath};
+use algorithmia::Algorithmia;
+use chrono::{DateTime, Utc};
+use docopt::Docopt;
+use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE, LAST_MODIFIED};
+use rustc_serialize::json::{Json, ToJson};
+use std::vec::IntoIter;
+
+static USAGE: &str = r##"Usage:
+  mia stat [options] <data-path>...
+
+  Display metadata of files or directories via the Agorithmia Data API (without downloading them)
+
+  <data-path>   Specifies the Algorithmia Data URI
+                The 'data://' prefix is optional
+
+  Options:
+    -c, --format <format>   Print metadata using <format> instead of the default, where:
+                              %n    data URI
+                              %N    name
+                              %F    type (file or directory)
+                              %s    size in bytes
+                              %h    size with k, M, or G suffix
+                              %y    last modified time
+                              %Y    last modified time as seconds since the epoch
+                              %A    read ACL (directories only)
+                              %C    content type (files only)
+                              %%    a literal %
+
+  Examples:
+    mia stat .my/foo/file1.jpg                           Show metadata of a file
+    mia stat -c '%s' .my/foo/file1.jpg                   Print the size of a file in bytes
+"##;
+
+#[derive(RustcDecodable, Debug)]
+struct Args {
+    arg_data_path: Vec<String>,
+    flag_format: Option<String>,
+}
+
+// Metadata of a file or directory, where unknown fields are None
+#[derive(Default)]
+struct Metadata {
+    data_uri: String,
+    name: String,
+    is_dir: bool,
+    size: Option<u64>,
+    modified: Option<Modified>,
+    acl: Option<String>,
+    content_type: Option<String>,
+}
+
+// Last modified time in each of the formats that are displayed
+struct Modified {
+    text: String,
+    rfc3339: String,
+    timestamp: i64,
+}
+
+impl From<DateTime<Utc>> for Modified {
+    fn from(time: DateTime<Utc>) -> Modified {
+        Modified {
+            text: time.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+            rfc3339: time.to_rfc3339(),
+            timestamp: time.timestamp(),
+        }
+    }
+}
+
+impl Metadata {
+    fn kind(&self) -> &'static str {
+        if self.is_dir {
+            "directory"
+        } else {
+            "file"
+        }
+    }
+
+    // Expands the `--format` directives, printing '-' for any unknown values
+    fn format(&self, format: &str) -> String {
+        let unknown = || "-".to_string();
+        let mut out = String::new();
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+            let value = match chars.next() {
+                Some('n') => self.data_uri.clone(),
+                Some('N') => self.name.clone(),
+                Some('F') => self.kind().to_string(),
+                Some('s') => self.size.map(|s| s.to_string()).unwrap_or_else(unknown),
+                Some('h') => self
+                    .size
+                    .map(data::size_with_suffix)
+                    .unwrap_or_else(unknown),
+                Some('y') => self
+                    .modified
+                    .as_ref()
+                    .map(|m| m.text.clone())
+                    .unwrap_or_else(unknown),
+                Some('Y') => self
+                    .modified
+                    .as_ref()
+                    .map(|m| m.timestamp.to_string())
+                    .unwrap_or_else(unknown),
+                Some('A') => self.acl.clone().unwrap_or_else(unknown),
+                Some('C') => self.content_type.clone().unwrap_or_else(unknown),
+                Some('%') => "%".to_string(),
+                Some(other) => format!("%{}", other),
+                None => "%".to_string(),
+            };
+            out.push_str(&value);
+        }
+        out
+    }
+
+    fn record(&self) -> Json {
+        output::object(vec![
+            ("data_uri", self.data_uri.to_json()),
+            ("name", self.name.to_json()),
+            ("type", if self.is_dir { "dir" } else { "file" }.to_json()),
+            ("size", self.size.to_json()),
+            (
+                "last_modified",
+                self.modified.as_ref().map(|m| m.rfc3339.clone()).to_json(),
+            ),
+            ("acl", self.acl.to_json()),
+            ("content_type", self.content_type.to_json()),
+        ])
+    }
+
+    fn print(&self) {
+        println!("         URI: {}", self.data_uri);
+        println!("        Type: {}", self.kind());
+        match self.size {
+            Some(size) if size < 1024 => println!("        Size: {}", size),
+            Some(size) => println!("        Size: {} ({})", size, data::size_with_suffix(size)),
+            None => (),
+        }
+        if let Some(ref modified) = self.modified {
+            println!("    Modified: {}", modified.text);
+        }
+        if let Some(ref acl) = self.acl {
+            println!("         ACL: {}", acl);
+        }
+        if let Some(ref content_type) = self.content_type {
+            println!("Content-Type: {}", content_type);
+        }
+    }
+}
+
+pub struct Stat {
+    client: Algorithmia,
+    api: ApiClient,
+}
+impl CmdRunner for Stat {
+    fn get_usage() -> &'static str {
+        USAGE
+    }
+
+    fn cmd_main(&self, argv: IntoIter<String>) {
+        let args: Args = Docopt::new(USAGE)
+            .and_then(|d| d.argv(argv).decode())
+            .unwrap_or_else(|e| e.exit());
+
+        let mut failed = false;
+        let records = Records::new();
+        for (i, path) in args.arg_data_path.iter().enumerate() {
+            match self.metadata(path) {
+                Ok(ref metadata) if !output::is_text() => records.add(metadata.record()),
+                Ok(metadata) => match args.flag_format {
+                    Some(ref format) => println!("{}", metadata.format(format)),
+                    None => {
+                        if i > 0 {
+                            println!();
+                        }
+                        metadata.print();
+                    }
+                },
+                Err(err) => {
+                    eprintln_red!("Cannot stat {}: {}", path, err);
+                    failed = true;
+                }
+            }
+        }
+
+        records.finish();
+
+        if failed {
+            ::std::process::exit(1);
+        }
+    }
+}
+
+impl Stat {
+    pub fn new(profile: Profile) -> Self {
+        Stat {
+            client: profile.client(),
+            api: profile.api_client(),
+        }
+    }
+
+    // A single HEAD request gives the type, size, content type, and modification time
+    fn metadata(&self, path: &str) -> Result<Metadata, String> {
+        let object = self.client.data(path);
+        let url = object.to_url().map_err(|err| err.to_string())?;
+        let headers = self.api.head(url.path()).map_err(|err| err.to_string())?;
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        match header("X-Data-Type") {
+            Some("directory") => {
+                let d = DataDir::from(object);
+                Ok(Metadata {
+                    data_uri: d.to_data_uri(),
+                    name: d.basename().unwrap_or_default(),
+                    is_dir: true,
+                    acl: data::get_dir_acl(&self.api, &d)
+                        .ok()
+                        .map(|read| data::read_acl_name(&read)),
+                    ..Metadata::default()
+                })
+            }
+            Some("file") => {
+                let f = DataFile::from(object);
+                // Listings also have the modification time, but reading one means paging
+                //   through the parent directory, so they're only used without Last-Modified
+                let modified = match header(LAST_MODIFIED.as_str()).and_then(data::parse_http_date)
+                {
+                    Some(modified) => Some(modified),
+                    None => match data::list_file(&f) {
+                        Ok(Some(listed)) => Some(listed.last_modified),
+                        _ => None,
+                    },
+                };
+                Ok(Metadata {
+                    data_uri: f.to_data_uri(),
+                    name: f.basename().unwrap_or_default(),
+                    is_dir: false,
+                    size: header(CONTENT_LENGTH.as_str()).and_then(|len| len.parse().ok()),
+                    modified: modified.map(Modified::from),
+                    acl: None,
+                    content_type: header(CONTENT_TYPE.as_str()).map(String::from),
+                })
+            }
+            Some(data_type) => Err(format!("Unknown data type '{}'", data_type)),
+            None => Err("No data type in the API response".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let metadata = Metadata {
+            data_uri: "data://.my/foo/bar.txt".into(),
+            name: "bar.txt".into(),
+            size: Some(2048),
+            ..Metadata::default()
+        };
+        assert_eq!(metadata.format("%N %s %h"), "bar.txt 2048 2.0k");
+        assert_eq!(
+            metadata.format("%F: %n (%y)"),
+            "file: data://.my/foo/bar.txt (-)"
+        );
+        assert_eq!(metadata.format("100%% %q%"), "100% %q%");
+    }
+}
@@ -0,0 +1,595 @@
+use super::cp::{create_local_dir, create_remote_dir, CpClient};
+use super::{is_remote, size_with_suffix};
+use crate::api::ApiClient;
+use crate::config::Profile;
+use crate::output::{self, Records};
+use crate::CmdRunner;
+use algorithmia::data::{DataDir, DataItem, HasDataPath};
+use algorithmia::Algorithmia;
+use docopt::Docopt;
+use rustc_serialize::json::{Json, ToJson};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+use std::vec::IntoIter;
+use std::{cmp, thread};
+
+static USAGE: &str = r##"Usage:
+  mia sync [options] <source> <dest>
+
+  Incrementally mirror a local directory to or from the Algorithmia Data API
+
+  Exactly one of <source> or <dest> must be prefixed with data:// (or another connector
+  scheme), which determines whether files are uploaded or downloaded. A file is only
+  transferred if it is missing from <dest>, differs in size, or is newer in <source>.
+
+  Like 'mia cp', progress is shown on stderr, and failed downloads are retried and resumed.
+  Files that still fail are listed once all other files have been synced.
+
+  Options:
+    -c <CONCURRENCY>      Number of threads for transferring in parallel [Default: 8]
+    --delete              Delete files in <dest> that no longer exist in <source>, and then
+                            the directories that no longer exist in <source>
+    -n, --dry-run         Print what would be transferred or deleted without making changes
+    --retries <RETRIES>   Number of times to retry a failed download [Default: 5]
+
+  Examples:
+    mia sync ./models data://.my/models                Upload new or changed model files
+    mia sync --delete data://.my/models ./models       Mirror a data directory locally
+"##;
+
+#[derive(RustcDecodable, Debug)]
+struct Args {
+    arg_source: String,
+    arg_dest: String,
+    flag_c: u32,
+    flag_delete: bool,
+    flag_dry_run: bool,
+    flag_retries: u32,
+}
+
+pub struct DataSync {
+    client: Algorithmia,
+    api: ApiClient,
+}
+
+impl CmdRunner for DataSync {
+    fn get_usage() -> &'static str {
+        USAGE
+    }
+
+    fn cmd_main(&self, argv: IntoIter<String>) {
+        let args: Args = Docopt::new(USAGE)
+            .and_then(|d| d.argv(argv).decode())
+            .unwrap_or_else(|e| e.exit());
+        let transfers = CpClient::for_transfers(
+            self.client.clone(),
+            self.api.clone(),
+            args.flag_c,
+            args.flag_retries,
+        );
+
+        let plan = match (is_remote(&args.arg_source), is_remote(&args.arg_dest)) {
+            (false, true) => self.plan_upload(&args.arg_source, &args.arg_dest, args.flag_delete),
+            (true, false) => self.plan_download(&args.arg_source, &args.arg_dest, args.flag_delete),
+            _ => quit_msg!(
+                "Exactly one of <source> or <dest> must be a data URI\n\n{}",
+                USAGE
+            ),
+        };
+
+        if args.flag_dry_run {
+            let records = Records::new();
+            for job in &plan.jobs {
+                records.report(&job.describe(), job.record("planned"));
+            }
+            records.finish();
+            if !output::is_text() {
+                return;
+            }
+            let dir_count = plan.jobs.iter().filter(|job| job.is_dir_delete()).count();
+            println!(
+                "Would transfer {} file(s) ({}B) and delete {} file(s) and {} dir(s)",
+                plan.transfer_count(),
+                size_with_suffix(plan.transfer_bytes),
+                plan.jobs.len() - plan.transfer_count() - dir_count,
+                dir_count,
+            );
+        } else {
+            self.execute(plan, transfers);
+        }
+    }
+}
+
+// Size and modification time (seconds since the epoch) of a file on either side of a sync
+#[derive(Debug, Clone, Copy)]
+struct FileStat {
+    size: u64,
+    modified: i64,
+}
+
+// Files keyed by their '/'-separated path relative to the root of the sync
+type FileListing = BTreeMap<String, FileStat>;
+
+// Directories by their '/'-separated path relative to the root of the sync
+type DirListing = BTreeSet<String>;
+
+// Transfers are (source, dest, size from the listing)
+//   Directories are only deleted once every other job is done, since they must be empty
+enum SyncJob {
+    Upload(PathBuf, String, u64),
+    Download(String, PathBuf, u64),
+    DeleteRemote(String),
+    DeleteLocal(PathBuf),
+    DeleteRemoteDir(String),
+    DeleteLocalDir(PathBuf),
+}
+
+impl SyncJob {
+    fn is_dir_delete(&self) -> bool {
+        matches!(
+            *self,
+            SyncJob::DeleteRemoteDir(_) | SyncJob::DeleteLocalDir(_)
+        )
+    }
+
+    fn action(&self) -> &'static str {
+        match *self {
+            SyncJob::Upload(..) => "upload",
+            SyncJob::Download(..) => "download",
+            _ => "delete",
+        }
+    }
+
+    // The file that a failure is reported for, i.e. the source of a transfer
+    fn source(&self) -> String {
+        match *self {
+            SyncJob::Upload(ref path, _, _)
+            | SyncJob::DeleteLocal(ref path)
+            | SyncJob::DeleteLocalDir(ref path) => path.to_string_lossy().into_owned(),
+            SyncJob::Download(ref uri, _, _)
+            | SyncJob::DeleteRemote(ref uri)
+            | SyncJob::DeleteRemoteDir(ref uri) => uri.clone(),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match *self {
+            SyncJob::Upload(ref src, ref dest, _) => {
+                format!("upload {} -> {}", src.display(), dest)
+            }
+            SyncJob::Download(ref src, ref dest, _) => {
+                format!("download {} -> {}", src, dest.display())
+            }
+            SyncJob::DeleteRemote(ref uri) => format!("delete {}", uri),
+            SyncJob::DeleteLocal(ref path) => format!("delete {}", path.display()),
+            SyncJob::DeleteRemoteDir(ref uri) => format!("delete directory {}", uri),
+            SyncJob::DeleteLocalDir(ref path) => format!("delete directory {}", path.display()),
+        }
+    }
+
+    fn record(&self, status: &str) -> Json {
+        let mut fields = match *self {
+            SyncJob::Upload(ref src, ref dest, _) => vec![
+                ("action", "upload".to_json()),
+                ("source", src.to_string_lossy().to_json()),
+                ("dest", dest.to_json()),
+            ],
+            SyncJob::Download(ref src, ref dest, _) => vec![
+                ("action", "download".to_json()),
+                ("source", src.to_json()),
+                ("dest", dest.to_string_lossy().to_json()),
+            ],
+            SyncJob::DeleteRemote(ref uri) | SyncJob::DeleteRemoteDir(ref uri) => {
+                vec![("action", "delete".to_json()), ("path", uri.to_json())]
+            }
+            SyncJob::DeleteLocal(ref path) | SyncJob::DeleteLocalDir(ref path) => vec![
+                ("action", "delete".to_json()),
+                ("path", path.to_string_lossy().to_json()),
+            ],
+        };
+        fields.push(("status", status.to_json()));
+        output::object(fields)
+    }
+}
+
+struct SyncPlan {
+    jobs: Vec<SyncJob>,
+    transfer_bytes: u64,
+    unchanged: usize,
+}
+
+impl SyncPlan {
+    fn transfer_count(&self) -> usize {
+        self.jobs
+            .iter()
+            .filter(|job| matches!(**job, SyncJob::Upload(..) | SyncJob::Download(..)))
+            .count()
+    }
+}
+
+impl DataSync {
+    pub fn new(profile: Profile) -> Self {
+        DataSync {
+            client: profile.client(),
+            api: profile.api_client(),
+        }
+    }
+
+    fn plan_upload(&self, local_root: &str, remote_root: &str, delete: bool) -> SyncPlan {
+        let local_root = Path::new(local_root);
+        if !local_root.is_dir() {
+            quit_msg!("{} is not a directory", local_root.display());
+        }
+        let remote_dir = self.client.dir(remote_root);
+
+        let (mut local, mut local_dirs) = (FileListing::new(), DirListing::new());
+        list_local(local_root, "", &mut local, &mut local_dirs);
+
+        let (mut remote, mut remote_dirs) = (FileListing::new(), DirListing::new());
+        match remote_dir.exists() {
+            Ok(true) => list_remote(&remote_dir, "", &mut remote, &mut remote_dirs),
+            Ok(false) => (),
+            Err(err) => quit_err!("Error checking {}: {}", remote_dir.to_data_uri(), err),
+        }
+
+        let (changed, deleted) = diff_listings(&local, &remote);
+        let mut plan = SyncPlan {
+            jobs: Vec::new(),
+            transfer_bytes: changed.iter().map(|path| local[*path].size).sum(),
+            unchanged: local.len() - changed.len(),
+        };
+        for path in changed {
+            plan.jobs.push(SyncJob::Upload(
+                local_root.join(path),
+                child_uri(&remote_dir, path),
+                local[path].size,
+            ));
+        }
+        if delete {
+            for path in deleted {
+                plan.jobs
+                    .push(SyncJob::DeleteRemote(child_uri(&remote_dir, path)));
+            }
+            for path in deleted_dirs(&local_dirs, &remote_dirs) {
+                plan.jobs
+                    .push(SyncJob::DeleteRemoteDir(child_uri(&remote_dir, path)));
+            }
+        }
+        plan
+    }
+
+    fn plan_download(&self, remote_root: &str, local_root: &str, delete: bool) -> SyncPlan {
+        let remote_dir = self.client.dir(remote_root);
+        let local_root = Path::new(local_root);
+
+        let (mut remote, mut remote_dirs) = (FileListing::new(), DirListing::new());
+        list_remote(&remote_dir, "", &mut remote, &mut remote_dirs);
+
+        let (mut local, mut local_dirs) = (FileListing::new(), DirListing::new());
+        if local_root.is_dir() {
+            list_local(local_root, "", &mut local, &mut local_dirs);
+        }
+
+        let (changed, deleted) = diff_listings(&remote, &local);
+        let mut plan = SyncPlan {
+            jobs: Vec::new(),
+            transfer_bytes: changed.iter().map(|path| remote[*path].size).sum(),
+            unchanged: remote.len() - changed.len(),
+        };
+        for path in changed {
+            plan.jobs.push(SyncJob::Download(
+                child_uri(&remote_dir, path),
+                local_root.join(path),
+                remote[path].size,
+            ));
+        }
+        if delete {
+            for path in deleted {
+                plan.jobs.push(SyncJob::DeleteLocal(local_root.join(path)));
+            }
+            for path in deleted_dirs(&remote_dirs, &local_dirs) {
+                plan.jobs
+                    .push(SyncJob::DeleteLocalDir(local_root.join(path)));
+            }
+        }
+        plan
+    }
+
+    fn execute(&self, plan: SyncPlan, transfers: CpClient) {
+        let unchanged = plan.unchanged;
+        let (dir_jobs, jobs): (Vec<SyncJob>, Vec<SyncJob>) =
+            plan.jobs.into_iter().partition(SyncJob::is_dir_delete);
+        let max_concurrency = transfers.max_concurrency();
+        let concurrency = cmp::min(jobs.len(), max_concurrency as usize);
+
+        // Create any missing parent directories up front so that workers don't race to create them
+        let mut created = BTreeSet::new();
+        for job in &jobs {
+            match *job {
+                SyncJob::Upload(_, ref uri, _) => {
+                    let parent = self.client.file(uri).parent().unwrap();
+                    if created.insert(parent.to_data_uri()) {
+                        create_remote_dirs(&parent);
+                    }
+                }
+                SyncJob::Download(_, ref path, _) => {
+                    let parent = path.parent().unwrap();
+                    if created.insert(parent.to_string_lossy().into_owned()) {
+                        create_local_dir(parent);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        let (tx, rx) = chan::sync(max_concurrency as usize);
+        let wg = chan::WaitGroup::new();
+        let transferred = Arc::new(Mutex::new(0));
+        let deleted = Arc::new(Mutex::new(0));
+
+        // One Producer thread queuing up the planned jobs
+        let producer_transfers = transfers.clone();
+        thread::spawn(move || {
+            for job in jobs {
+                match job {
+                    SyncJob::Upload(_, _, size) | SyncJob::Download(_, _, size) => {
+                        producer_transfers.progress().queue(Some(size))
+                    }
+                    _ => (),
+                }
+                tx.send(job);
+            }
+            drop(tx);
+        });
+
+        // Spin up threads to concurrently process jobs received on rx channel
+        for _ in 0..concurrency {
+            wg.add(1);
+
+            let thread_wg = wg.clone();
+            let thread_rx = rx.clone();
+            let thread_client = self.client.clone();
+            let thread_transferred = transferred.clone();
+            let thread_deleted = deleted.clone();
+            let thread_transfers = transfers.clone();
+
+            thread::spawn(move || {
+                for job in thread_rx {
+                    let count = match job {
+                        SyncJob::Upload(..) | SyncJob::Download(..) => &thread_transferred,
+                        _ => &thread_deleted,
+                    };
+                    run_job(&thread_client, &thread_transfers, &job, count);
+                }
+                thread_wg.done();
+            });
+        }
+        wg.wait();
+
+        // Each directory is deleted after its files and subdirectories
+        for job in &dir_jobs {
+            run_job(&self.client, &transfers, job, &deleted);
+        }
+
+        transfers.progress().finish();
+        transfers.records().finish();
+        if output::is_text() {
+            println!(
+                "Finished syncing: {} transferred, {} deleted, {} unchanged, {}",
+                *transferred.lock().unwrap(),
+                *deleted.lock().unwrap(),
+                unchanged,
+                transfers.progress().summary()
+            );
+        }
+        transfers.exit_if_failed("sync");
+    }
+}
+
+// Runs a single job, counting it if it succeeds. Failures are reported like those of
+//   `mia cp`, and don't stop the other jobs.
+fn run_job(client: &Algorithmia, transfers: &CpClient, job: &SyncJob, count: &Mutex<u32>) {
+    let res = match *job {
+        SyncJob::Upload(ref src, ref dest, size) => {
+            let local_path = src.to_string_lossy();
+            let file = client.file(dest);
+            transfers
+                .upload_file(&local_path, &file, Some(size))
+                .map(|_| format!("Uploaded {}", dest))
+        }
+        SyncJob::Download(ref src, ref dest, size) => {
+            let local_path = dest.to_string_lossy();
+            let file = client.file(src);
+            transfers
+                .download_file(&file, &local_path, Some(size))
+                .map(|bytes| format!("Downloaded {} ({}B)", src, size_with_suffix(bytes)))
+        }
+        SyncJob::DeleteRemote(ref uri) => client
+            .file(uri)
+            .delete()
+            .map(|_| format!("Deleted {}", uri))
+            .map_err(|err| err.to_string()),
+        SyncJob::DeleteLocal(ref path) => fs::remove_file(path)
+            .map(|_| format!("Deleted {}", path.display()))
+            .map_err(|err| err.to_string()),
+        // Not forced, so that a directory still holding a file that failed to delete is kept
+        SyncJob::DeleteRemoteDir(ref uri) => client
+            .dir(uri)
+            .delete(false)
+            .map(|_| format!("Deleted {}", uri))
+            .map_err(|err| err.to_string()),
+        SyncJob::DeleteLocalDir(ref path) => fs::remove_dir(path)
+            .map(|_| format!("Deleted {}", path.display()))
+            .map_err(|err| err.to_string()),
+    };
+
+    let progress = transfers.progress();
+    match res {
+        Ok(msg) => {
+            if matches!(*job, SyncJob::Upload(..) | SyncJob::Download(..)) {
+                progress.complete();
+            }
+            progress.suspend(|| transfers.records().report(&msg, job.record("ok")));
+            *count.lock().unwrap() += 1;
+        }
+        Err(err) => transfers.report_failure(job.action(), &job.source(), err),
+    }
+}
+
+fn child_uri(dir: &DataDir, rel_path: &str) -> String {
+    match dir.to_data_uri() {
+        ref uri if uri.ends_with('/') => format!("{}{}", uri, rel_path),
+        uri => format!("{}/{}", uri, rel_path),
+    }
+}
+
+// Recursively creates a remote directory and any missing ancestors
+fn create_remote_dirs(dir: &DataDir) {
+    if let Ok(true) = dir.exists() {
+        return;
+    }
+    if let Some(parent) = dir.parent() {
+        create_remote_dirs(&parent);
+    }
+    create_remote_dir(dir);
+}
+
+fn join_rel(prefix: &str, name: &str) -> String {
+    match prefix {
+        "" => name.to_string(),
+        p => format!("{}/{}", p, name),
+    }
+}
+
+fn list_local(dir: &Path, prefix: &str, listing: &mut FileListing, dirs: &mut DirListing) {
+    let entries = fs::read_dir(dir)
+        .unwrap_or_else(|err| quit_err!("Error reading directory {}: {}", dir.display(), err));
+    for entry in entries {
+        let entry = entry
+            .unwrap_or_else(|err| quit_err!("Error reading directory {}: {}", dir.display(), err));
+        let rel_path = join_rel(prefix, &entry.file_name().to_string_lossy());
+        let metadata = entry
+            .metadata()
+            .unwrap_or_else(|err| quit_err!("Error reading {}: {}", rel_path, err));
+        if metadata.is_dir() {
+            list_local(&entry.path(), &rel_path, listing, dirs);
+            dirs.insert(rel_path);
+        } else {
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0);
+            listing.insert(
+                rel_path,
+                FileStat {
+                    size: metadata.len(),
+                    modified,
+                },
+            );
+        }
+    }
+}
+
+fn list_remote(dir: &DataDir, prefix: &str, listing: &mut FileListing, dirs: &mut DirListing) {
+    for entry_result in dir.list() {
+        match entry_result {
+            Ok(DataItem::Dir(d)) => {
+                let rel_path = join_rel(prefix, &d.basename().unwrap());
+                list_remote(&d, &rel_path, listing, dirs);
+                dirs.insert(rel_path);
+            }
+            Ok(DataItem::File(f)) => {
+                listing.insert(
+                    join_rel(prefix, &f.basename().unwrap()),
+                    FileStat {
+                        size: f.size,
+                        modified: f.last_modified.timestamp(),
+                    },
+                );
+            }
+            Err(err) => quit_err!("Error listing directory: {}", err),
+        }
+    }
+}
+
+// Compares the source listing against the dest listing, returning the paths that need to be
+//   transferred and the paths that only exist in the dest
+fn diff_listings<'a>(
+    source: &'a FileListing,
+    dest: &'a FileListing,
+) -> (Vec<&'a str>, Vec<&'a str>) {
+    let changed = source
+        .iter()
+        .filter(|&(path, src)| match dest.get(path) {
+            Some(dst) => src.size != dst.size || src.modified > dst.modified,
+            None => true,
+        })
+        .map(|(path, _)| path.as_str())
+        .collect();
+    let deleted = dest
+        .keys()
+        .filter(|path| !source.contains_key(*path))
+        .map(String::as_str)
+        .collect();
+    (changed, deleted)
+}
+
+// The directories that only exist in the dest, with subdirectories before their parents
+fn deleted_dirs<'a>(source: &DirListing, dest: &'a DirListing) -> Vec<&'a str> {
+    dest.iter()
+        .rev()
+        .filter(|path| !source.contains(*path))
+        .map(String::as_str)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn listing(entries: &[(&str, u64, i64)]) -> FileListing {
+        entries
+            .iter()
+            .map(|&(path, size, modified)| (path.to_string(), FileStat { size, modified }))
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_listings() {
+        let source = listing(&[
+            ("same.txt", 10, 100),
+            ("resized.txt", 20, 100),
+            ("newer.txt", 10, 200),
+            ("older.txt", 10, 50),
+            ("a/new.txt", 5, 100),
+        ]);
+        let dest = listing(&[
+            ("same.txt", 10, 100),
+            ("resized.txt", 10, 100),
+            ("newer.txt", 10, 100),
+            ("older.txt", 10, 100),
+            ("a/stale.txt", 5, 100),
+        ]);
+
+        let (changed, deleted) = diff_listings(&source, &dest);
+        assert_eq!(changed, vec!["a/new.txt", "newer.txt", "resized.txt"]);
+        assert_eq!(deleted, vec!["a/stale.txt"]);
+    }
+
+    #[test]
+    fn test_deleted_dirs() {
+        let dirs = |paths: &[&str]| paths.iter().map(|p| p.to_string()).collect::<DirListing>();
+        let source = dirs(&["a", "a/kept"]);
+        let dest = dirs(&["a", "a/kept", "a/gone", "a/gone/deeper", "b", "b/c", "ab"]);
+
+        assert_eq!(
+            deleted_dirs(&source, &dest),
+            vec!["b/c", "b", "ab", "a/gone/deeper", "a/gone"]
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use super::ls::FileType;
+use crate::config::Profile;
+use crate::output::{self, Records};
+use crate::{color_choice, data, CmdRunner};
+use algorithmia::data::{DataDir, DataItem, HasDataPath};
+use algorithmia::Algorithmia;
+use docopt::Docopt;
+use rustc_serialize::json::ToJson;
+use std::io::Write;
+use std::vec::IntoIter;
+use termcolor::{Color, ColorSpec, StandardStream, WriteColor};
+
+static USAGE: &str = r##"Usage:
+  mia tree [options] [<data-dir>]
+
+  Recursively list the contents of a directory as a tree via the Agorithmia Data API
+
+  <data-dir>    Specifies the Algorithmia Data URI
+                The 'data://' prefix is optional
+                Defaults to 'data://' root path
+
+  Options:
+    -s, --size              Print the size of each file
+    --max-depth <depth>     Descend at most <depth> directory levels
+
+  Examples:
+    mia tree .my/foo                                     Show everything under 'foo'
+    mia tree --max-depth 1 .my                           Show the top level of each of your directories
+"##;
+
+#[derive(RustcDecodable, Debug)]
+struct Args {
+    arg_data_dir: Option<String>,
+    flag_size: bool,
+    flag_max_depth: Option<usize>,
+}
+
+pub struct Tree {
+    client: Algorithmia,
+}
+
+impl CmdRunner for Tree {
+    fn get_usage() -> &'static str {
+        USAGE
+    }
+
+    fn cmd_main(&self, argv: IntoIter<String>) {
+        let args: Args = Docopt::new(USAGE)
+            .and_then(|d| d.argv(argv).decode())
+            .unwrap_or_else(|e| e.exit());
+
+        let data_uri = args.arg_data_dir.as_deref().unwrap_or("data://");
+        self.print_tree(data_uri, args.flag_size, args.flag_max_depth);
+    }
+}
+
+impl Tree {
+    pub fn new(profile: Profile) -> Self {
+        Tree {
+            client: profile.client(),
+        }
+    }
+
+    fn print_tree(&self, path: &str, show_size: bool, max_depth: Option<usize>) {
+        let my_dir = self.client.dir(path);
+        if !output::is_text() {
+            return self.print_records(&my_dir, max_depth);
+        }
+
+        let mut t_out = StandardStream::stdout(color_choice());
+        let _ = t_out.set_color(ColorSpec::new().set_fg(Some(Color::Blue)));
+        let _ = writeln!(t_out, "{}", my_dir.to_data_uri());
+        let _ = t_out.reset();
+
+        // Whether each ancestor of the current item was the last in its directory
+        let mut last_stack: Vec<bool> = Vec::new();
+        let mut summary = data::Summary::default();
+        let res = data::walk_dir(&my_dir, max_depth, &mut |item, depth, is_last| {
+            last_stack.truncate(depth - 1);
+            for &ancestor_last in &last_stack {
+                let _ = write!(t_out, "{}", if ancestor_last { "    " } else { "│   " });
+            }
+            let _ = write!(t_out, "{}", if is_last { "└── " } else { "├── " });
+            last_stack.push(is_last);
+            summary.add(item);
+
+            match *item {
+                DataItem::Dir(ref d) => {
+                    let _ = t_out.set_color(ColorSpec::new().set_fg(Some(Color::Blue)));
+                    let _ = write!(t_out, "{}", d.basename().unwrap());
+                }
+                DataItem::File(ref f) => {
+                    if show_size {
+                        let _ = write!(t_out, "[{:>5}]  ", data::size_with_suffix(f.size));
+                    }
+                    let name = f.basename().unwrap();
+                    let c = FileType::from_filename(&name).to_color();
+                    let _ = t_out.set_color(ColorSpec::new().set_fg(c));
+                    let _ = write!(t_out, "{}", name);
+                }
+            }
+            let _ = t_out.reset();
+            let _ = writeln!(t_out);
+        });
+
+        if let Err(err) = res {
+            quit_msg!("Error listing directory: {}", err);
+        }
+        let _ = writeln!(t_out, "\n{}", summary);
+    }
+
+    // Structured output of each item along with its depth below the root
+    fn print_records(&self, dir: &DataDir, max_depth: Option<usize>) {
+        let records = Records::new();
+        let res = data::walk_dir(dir, max_depth, &mut |item, depth, _| {
+            let mut record = data::item_record(item);
+            output::insert(&mut record, "depth", depth.to_json());
+            records.add(record);
+        });
+        if let Err(err) = res {
+            quit_msg!("Error listing directory: {}", err);
+        }
+        records.finish();
+    }
+}
@@ -0,0 +1,248 @@
+use crate::api::ApiClient;
+use algorithmia::data::{DataFile, HasDataPath};
+use reqwest::header::{HeaderMap, CONTENT_LENGTH};
+use rustc_serialize::base64::FromBase64;
+use rustc_serialize::hex::ToHex;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+// Which check confirmed that a copy matches its source, or that none could be made because
+//   the API supplied neither a size nor an MD5
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum Verified {
+    Size,
+    Md5,
+    Unverified,
+}
+
+impl fmt::Display for Verified {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Verified::Size => write!(f, "size"),
+            Verified::Md5 => write!(f, "md5"),
+            Verified::Unverified => write!(f, "none"),
+        }
+    }
+}
+
+// Size and content hash of a remote file, where the hash is only known if the API supplies one
+struct RemoteDigest {
+    size: Option<u64>,
+    md5: Option<String>,
+}
+
+// Reads the size and hash of a remote file from the headers of a HEAD request,
+//   which is also where the size of a `DataFile` comes from
+fn remote_digest(api: &ApiClient, file: &DataFile) -> Result<RemoteDigest, String> {
+    let url = file.to_url().map_err(|err| err.to_string())?;
+    let headers = api
+        .head(url.path())
+        .map_err(|err| format!("Error reading {}: {}", file.to_data_uri(), err))?;
+    Ok(header_digest(&headers))
+}
+
+// Only Content-MD5 is documented as an MD5 of the content. ETags are never used: weak ETags
+//   aren't content hashes at all, and S3 ETags of SSE-KMS/SSE-C objects look like MD5s but aren't.
+fn header_digest(headers: &HeaderMap) -> RemoteDigest {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    RemoteDigest {
+        size: header(CONTENT_LENGTH.as_str()).and_then(|len| len.parse().ok()),
+        md5: header("content-md5").and_then(content_md5),
+    }
+}
+
+// Content-MD5 is the base64 encoding of the 16 byte digest (RFC 1864), returned here as hex
+fn content_md5(value: &str) -> Option<String> {
+    match value.trim().from_base64() {
+        Ok(ref digest) if digest.len() == 16 => Some(digest.to_hex()),
+        _ => None,
+    }
+}
+
+fn local_md5(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut context = md5::Context::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        match file.read(&mut buf)? {
+            0 => break,
+            n => context.consume(&buf[..n]),
+        }
+    }
+    Ok(format!("{:x}", context.compute()))
+}
+
+// Checks that a local file and a remote file have the same size, and the same MD5
+//   if the API supplies one. Local checksums are recorded in the manifest (if any).
+pub(super) fn verify_local(
+    api: &ApiClient,
+    remote: &DataFile,
+    local: &Path,
+    manifest: Option<&Manifest>,
+) -> Result<Verified, String> {
+    let digest = remote_digest(api, remote)?;
+    let local_size = fs::metadata(local)
+        .map(|m| m.len())
+        .map_err(|err| format!("Error reading {}: {}", local.display(), err))?;
+    if let Some(size) = digest.size {
+        if size != local_size {
+            return Err(format!(
+                "Size mismatch: {} has {} bytes, but {} has {}",
+                local.display(),
+                local_size,
+                remote.to_data_uri(),
+                size
+            ));
+        }
+    }
+
+    let unchecked = if digest.size.is_some() {
+        Verified::Size
+    } else {
+        Verified::Unverified
+    };
+    if digest.md5.is_none() && manifest.is_none() {
+        return Ok(unchecked);
+    }
+    let md5 =
+        local_md5(local).map_err(|err| format!("Error reading {}: {}", local.display(), err))?;
+    if let Some(manifest) = manifest {
+        manifest.add(&md5, local);
+    }
+    match digest.md5 {
+        Some(ref remote_md5) if *remote_md5 != md5 => Err(format!(
+            "MD5 mismatch: {} is {}, but {} is {}",
+            local.display(),
+            md5,
+            remote.to_data_uri(),
+            remote_md5
+        )),
+        Some(_) => Ok(Verified::Md5),
+        None => Ok(unchecked),
+    }
+}
+
+// Checks that two remote files have the same size, and the same MD5 if the API supplies both
+pub(super) fn verify_remote(
+    api: &ApiClient,
+    source: &DataFile,
+    dest: &DataFile,
+) -> Result<Verified, String> {
+    let (src, dst) = (remote_digest(api, source)?, remote_digest(api, dest)?);
+    if src.size != dst.size {
+        return Err(format!(
+            "Size mismatch: {} has {} bytes, but {} has {}",
+            source.to_data_uri(),
+            src.size
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| "?".into()),
+            dest.to_data_uri(),
+            dst.size
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| "?".into())
+        ));
+    }
+    match (src.md5, dst.md5) {
+        (Some(ref a), Some(ref b)) if a != b => Err(format!(
+            "MD5 mismatch: {} is {}, but {} is {}",
+            source.to_data_uri(),
+            a,
+            dest.to_data_uri(),
+            b
+        )),
+        (Some(_), Some(_)) => Ok(Verified::Md5),
+        _ if src.size.is_none() => Ok(Verified::Unverified),
+        _ => Ok(Verified::Size),
+    }
+}
+
+// MD5 checksums of local files, written in the format of `md5sum` so they can be checked later
+//   with `md5sum -c <manifest>`
+pub(super) struct Manifest {
+    path: String,
+    entries: Mutex<Vec<(String, String)>>,
+}
+
+impl Manifest {
+    pub fn new(path: &str) -> Manifest {
+        Manifest {
+            path: path.to_string(),
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn add(&self, md5: &str, path: &Path) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.push((md5.to_string(), path.to_string_lossy().into_owned()));
+    }
+
+    // Writes the checksums sorted by path, since files finish in any order
+    pub fn write(&self) -> io::Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.sort_by(|a, b| a.1.cmp(&b.1));
+        let mut file = File::create(&self.path)?;
+        for (md5, path) in entries.iter() {
+            writeln!(file, "{}  {}", md5, path)?;
+        }
+        Ok(())
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_content_md5() {
+        assert_eq!(
+            content_md5("1B2M2Y8AsgTpgAmY7PhCfg==").as_deref(),
+            Some("d41d8cd98f00b204e9800998ecf8427e")
+        );
+        assert_eq!(content_md5("d41d8cd98f00b204e9800998ecf8427e"), None);
+        assert_eq!(content_md5("1B2M2Y8AsgTpgAmY7PhC!g=="), None);
+        assert_eq!(content_md5(""), None);
+    }
+
+    #[test]
+    fn test_header_digest() {
+        let headers = |pairs: &[(&'static str, &'static str)]| {
+            let mut headers = HeaderMap::new();
+            for (name, value) in pairs {
+                headers.insert(*name, HeaderValue::from_static(value));
+            }
+            header_digest(&headers)
+        };
+
+        let digest = headers(&[
+            ("content-length", "0"),
+            ("content-md5", "1B2M2Y8AsgTpgAmY7PhCfg=="),
+        ]);
+        assert_eq!(digest.size, Some(0));
+        assert_eq!(
+            digest.md5.as_deref(),
+            Some("d41d8cd98f00b204e9800998ecf8427e")
+        );
+
+        // Neither weak ETags nor ETags that look like MD5s (e.g. S3 SSE-KMS) are trusted
+        let weak = headers(&[
+            ("content-length", "0"),
+            ("etag", "W/\"d41d8cd98f00b204e9800998ecf8427e\""),
+        ]);
+        assert_eq!(weak.size, Some(0));
+        assert_eq!(weak.md5, None);
+        let kms = headers(&[
+            ("content-length", "5"),
+            ("etag", "\"8f4e3a5fc8a1f96c0e2b0c3b5e1d2f7a\""),
+        ]);
+        assert_eq!(kms.size, Some(5));
+        assert_eq!(kms.md5, None);
+    }
+}
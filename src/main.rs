@@ -2,6 +2,7 @@
 extern crate serde_derive;
 
 use crate::config::Profile;
+use crate::output::OutputFormat;
 use isatty::stderr_isatty;
 use std::env;
 use std::error::Error as StdError;
@@ -70,9 +71,13 @@ macro_rules! quit_err {
 }
 
 mod algo;
+mod api;
 mod auth;
 pub mod config;
+mod credential;
 mod data;
+mod output;
+mod profile;
 mod version; // Module is generated by cargo build script
 
 static ASCII_ART: &'static str = include_str!("mia.txt");
@@ -86,34 +91,52 @@ Usage:
 
 General commands include:
   auth      Configure authentication
+  whoami    Check the credentials of a profile with the server
+  profile   List, show, remove, rename, or set the default of configured profiles
 
 Algorithm commands include:
   run       Runs an algorithm
+  runlocal  Builds and runs an algorithm from a local checkout
+  serve     Serves an algorithm from a local checkout over HTTP
   clone     Clones an algorithm source
+  fork      Forks an algorithm (and optionally clones it)
+  view      View algorithm details (e.g. versions and cost)
 
 Data commands include
   ls        List contents of a data directory
+  tree      Recursively list contents of a data directory as a tree
+  du        Summarize storage used by data directories
+  find      Search a data directory by name, size, or modification time
+  stat      Display metadata of a file or directory
   mkdir     Create a data directory
   rmdir     Delete a data directory
-  rm        Remove a file from a data directory
+  rm        Remove file(s) or directories from a data directory
   cp        Copy file(s) to or from a data directory
+  mv        Move or rename file(s) and directories in a data directory
+  sync      Incrementally mirror a directory to or from a data directory
   cat       Concatenate and print file(s) in a data directory
+  chmod     Change permissions of a data directory
 
 Global options:
   --help                Prints the help for a particular command
   --profile <name>      Run a particular command for the specified profile
+  --output-format <fmt> Print results of data commands as text (default), json, or jsonl
+
+Environment variables:
+  ALGORITHMIA_API_KEY   API key, which takes precedence over the profile's (and is enough
+                          to run commands without a config file)
+  ALGORITHMIA_API       API server, which takes precedence over the profile's
+  ALGORITHMIA_GIT       Git server, which takes precedence over the profile's (if only
+                          ALGORITHMIA_API is set, the git server is derived from it)
+  MIA_PROFILE           Profile to use when --profile isn't given (instead of the default
+                          set by 'mia profile default', or 'default')
+  MIA_CONFIG            Path of the config file (instead of $HOME/.algorithmia/config)
 "##;
 
 // TODO: Add support for:
 //
-// Algorithm commands include:
-// view      View algorithm details (e.g. cost)
-// fork      Fork an algorithm
-//
 // Data commands include:
 // download  Download file(s) from a collection
-// rm        Delete file(s) in a collection
-// chmod     Change permissions on a collection
 //
 
 fn print_usage() -> ! {
@@ -128,8 +151,12 @@ struct MainArgs {
     flag_h: bool,
 }
 
+pub(crate) fn is_stderr_tty() -> bool {
+    stderr_isatty()
+}
+
 pub(crate) fn color_choice() -> ColorChoice {
-    if stderr_isatty() {
+    if is_stderr_tty() {
         ColorChoice::Auto
     } else {
         ColorChoice::Never
@@ -139,7 +166,7 @@ pub(crate) fn color_choice() -> ColorChoice {
 fn main() {
     let mut args = env::args().peekable();
     let mut cmd_args: Vec<String> = Vec::new();
-    let mut profile = "default".to_string();
+    let mut profile = config::default_profile_name();
 
     // Search for global options, push everything else onto cmd_args
     while let Some(arg) = args.next() {
@@ -150,6 +177,16 @@ fn main() {
                 print_cmd_usage(cmd_args.get(1).map(|s| s.as_str()));
             }
             "--profile" => profile = args.next().unwrap_or_else(|| profile.to_string()),
+            "--output-format" => {
+                let name = args.next().unwrap_or_default();
+                match OutputFormat::parse(&name) {
+                    Some(format) => output::set_output_format(format),
+                    None => quit_msg!(
+                        "Invalid output format '{}'. Expected one of: text, json, jsonl",
+                        name
+                    ),
+                }
+            }
             "--version" => {
                 match color_choice() {
                     ColorChoice::Never => eprint!("{}", ASCII_ART),
@@ -177,18 +214,30 @@ fn run(args: Vec<String>, profile_name: &str) {
 
     let args_iter = args.into_iter();
     match &*cmd {
-        "auth" => auth::Auth::new(profile_name).cmd_main(args_iter),
+        "auth" | "whoami" => auth::Auth::new(profile_name).cmd_main(args_iter),
+        "profile" => profile::Profiles::new(profile_name).cmd_main(args_iter),
+        "runlocal" => algo::RunLocal::new().cmd_main(args_iter),
+        "serve" => algo::Serve::new().cmd_main(args_iter),
         _ => {
             let profile = Profile::lookup(profile_name);
             match &*cmd {
                 "clone" => algo::GitClone::new(profile).cmd_main(args_iter),
+                "fork" => algo::Fork::new(profile).cmd_main(args_iter),
                 "ls" | "dir" => data::Ls::new(profile).cmd_main(args_iter),
                 "mkdir" => data::MkDir::new(profile).cmd_main(args_iter),
                 "rmdir" => data::RmDir::new(profile).cmd_main(args_iter),
                 "rm" => data::Rm::new(profile).cmd_main(args_iter),
                 "cp" | "copy" => data::Cp::new(profile).cmd_main(args_iter),
+                "mv" | "move" => data::Mv::new(profile).cmd_main(args_iter),
+                "tree" => data::Tree::new(profile).cmd_main(args_iter),
+                "du" => data::Du::new(profile).cmd_main(args_iter),
+                "find" => data::Find::new(profile).cmd_main(args_iter),
+                "stat" => data::Stat::new(profile).cmd_main(args_iter),
+                "sync" => data::DataSync::new(profile).cmd_main(args_iter),
                 "cat" => data::Cat::new(profile).cmd_main(args_iter),
+                "chmod" => data::ChMod::new(profile).cmd_main(args_iter),
                 "run" => algo::Run::new(profile).cmd_main(args_iter),
+                "view" | "info" => algo::View::new(profile).cmd_main(args_iter),
                 _ => print_usage(),
             }
         }
@@ -197,15 +246,27 @@ fn run(args: Vec<String>, profile_name: &str) {
 
 fn print_cmd_usage(cmd: Option<&str>) -> ! {
     match cmd.unwrap_or_else(Default::default) {
-        "auth" => auth::Auth::print_usage(),
+        "auth" | "whoami" => auth::Auth::print_usage(),
+        "profile" => profile::Profiles::print_usage(),
         "ls" | "dir" => data::Ls::print_usage(),
         "mkdir" => data::MkDir::print_usage(),
         "rmdir" => data::RmDir::print_usage(),
         "rm" => data::Rm::print_usage(),
         "cp" | "copy" => data::Cp::print_usage(),
+        "mv" | "move" => data::Mv::print_usage(),
+        "tree" => data::Tree::print_usage(),
+        "du" => data::Du::print_usage(),
+        "find" => data::Find::print_usage(),
+        "stat" => data::Stat::print_usage(),
+        "sync" => data::DataSync::print_usage(),
         "cat" => data::Cat::print_usage(),
+        "chmod" => data::ChMod::print_usage(),
         "clone" => algo::GitClone::print_usage(),
+        "fork" => algo::Fork::print_usage(),
         "run" => algo::Run::print_usage(),
+        "runlocal" => algo::RunLocal::print_usage(),
+        "serve" => algo::Serve::print_usage(),
+        "view" | "info" => algo::View::print_usage(),
         _ => print_usage(),
     };
 }
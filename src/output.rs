@@ -0,0 +1,106 @@
+//! Structured output selected by the global `--output-format` option
+//!
+//! Commands print human-readable text by default. With `json`, data commands print their results
+//! as a single JSON array when finished, and with `jsonl`, one JSON object per line as they happen.
+use rustc_serialize::json::Json;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    JsonLines,
+}
+
+static OUTPUT_FORMAT: AtomicUsize = AtomicUsize::new(0);
+
+impl OutputFormat {
+    pub fn parse(name: &str) -> Option<OutputFormat> {
+        match name {
+            "text" => Some(OutputFormat::Text),
+            "json" => Some(OutputFormat::Json),
+            "jsonl" => Some(OutputFormat::JsonLines),
+            _ => None,
+        }
+    }
+}
+
+pub fn set_output_format(format: OutputFormat) {
+    OUTPUT_FORMAT.store(format as usize, Ordering::SeqCst);
+}
+
+pub fn output_format() -> OutputFormat {
+    match OUTPUT_FORMAT.load(Ordering::SeqCst) {
+        1 => OutputFormat::Json,
+        2 => OutputFormat::JsonLines,
+        _ => OutputFormat::Text,
+    }
+}
+
+/// True unless structured output was requested
+pub fn is_text() -> bool {
+    output_format() == OutputFormat::Text
+}
+
+/// Builds a JSON object from key/value pairs
+pub fn object(pairs: Vec<(&str, Json)>) -> Json {
+    Json::Object(
+        pairs
+            .into_iter()
+            .map(|(k, v)| (k.to_owned(), v))
+            .collect::<BTreeMap<_, _>>(),
+    )
+}
+
+/// Adds a field to a JSON object built by `object`
+pub fn insert(record: &mut Json, key: &str, value: Json) {
+    if let Json::Object(ref mut map) = *record {
+        map.insert(key.to_owned(), value);
+    }
+}
+
+/// Reports the only result of a command (see `Records::report`)
+pub fn report(text: &str, record: Json) {
+    let records = Records::new();
+    records.report(text, record);
+    records.finish();
+}
+
+/// Results of a command, printed as a JSON array by `finish` or as JSON lines as they're added
+///
+/// Records may be added from multiple threads.
+#[derive(Default)]
+pub struct Records {
+    records: Mutex<Vec<Json>>,
+}
+
+impl Records {
+    pub fn new() -> Records {
+        Records::default()
+    }
+
+    /// Prints `text` for text output, otherwise adds the record
+    pub fn report(&self, text: &str, record: Json) {
+        match output_format() {
+            OutputFormat::Text => println!("{}", text),
+            _ => self.add(record),
+        }
+    }
+
+    pub fn add(&self, record: Json) {
+        match output_format() {
+            OutputFormat::JsonLines => println!("{}", record),
+            OutputFormat::Json => self.records.lock().unwrap().push(record),
+            OutputFormat::Text => (),
+        }
+    }
+
+    pub fn finish(&self) {
+        if output_format() == OutputFormat::Json {
+            let records = self.records.lock().unwrap();
+            println!("{}", Json::Array(records.clone()));
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use crate::config::{self, Config, Profile};
+use crate::credential::{self, ApiKeySource};
+use crate::CmdRunner;
+use docopt::Docopt;
+use std::vec::IntoIter;
+
+static USAGE: &str = r##"
+Usage:
+  mia profile list
+  mia profile show [<name>]
+  mia profile rm <name>
+  mia profile rename <old-name> <new-name>
+  mia profile default [<name>]
+
+  Manages the profiles configured with 'mia auth'.
+
+  Commands:
+    list        List profiles, marking the default with '*'
+    show        Show the settings of a profile (or the one selected by --profile),
+                  with the API key masked
+    rm          Remove a profile
+    rename      Rename a profile
+    default     Show the default profile, or make the named profile the default, which
+                  is used when neither --profile nor MIA_PROFILE is given
+
+  Examples:
+    mia profile default prod                  Use the 'prod' profile unless another is specified
+    mia profile rename default staging        Rename the 'default' profile to 'staging'
+"##;
+
+#[derive(RustcDecodable, Debug)]
+struct Args {
+    cmd_list: bool,
+    cmd_show: bool,
+    cmd_rm: bool,
+    cmd_rename: bool,
+    cmd_default: bool,
+    arg_name: Option<String>,
+    arg_old_name: String,
+    arg_new_name: String,
+}
+
+pub struct Profiles {
+    profile: String,
+}
+impl CmdRunner for Profiles {
+    fn get_usage() -> &'static str {
+        USAGE
+    }
+
+    fn cmd_main(&self, argv: IntoIter<String>) {
+        let args: Args = Docopt::new(USAGE)
+            .and_then(|d| d.argv(argv).decode())
+            .unwrap_or_else(|e| e.exit());
+
+        let mut config = Config::read_config().unwrap_or_default();
+        if args.cmd_list {
+            list(&config);
+        } else if args.cmd_show {
+            show(&config, args.arg_name.as_deref().unwrap_or(&self.profile));
+        } else if args.cmd_rm {
+            let name = args.arg_name.unwrap_or_default();
+            let removed = config
+                .remove_profile(&name)
+                .unwrap_or_else(|| quit_msg!("Profile '{}' not found", name));
+            config.write_config();
+            if let Some(ApiKeySource::Keyring(account)) = removed.api_key_source() {
+                if let Err(err) = credential::keyring_delete(&account) {
+                    eprintln!("Cannot remove the API key from the OS keyring: {}", err);
+                }
+            }
+            println!("Removed profile '{}'", name);
+        } else if args.cmd_rename {
+            config
+                .rename_profile(&args.arg_old_name, &args.arg_new_name)
+                .unwrap_or_else(|err| quit_msg!("{}", err));
+            config.write_config();
+            println!(
+                "Renamed profile '{}' to '{}'",
+                args.arg_old_name, args.arg_new_name
+            );
+        } else if args.cmd_default {
+            match args.arg_name {
+                Some(name) => {
+                    config
+                        .set_default_profile(&name)
+                        .unwrap_or_else(|err| quit_msg!("{}", err));
+                    config.write_config();
+                    println!("Default profile is now '{}'", name);
+                }
+                None => println!("{}", config.default_profile()),
+            }
+        }
+    }
+}
+
+impl Profiles {
+    pub fn new(profile: &str) -> Self {
+        Profiles {
+            profile: profile.to_owned(),
+        }
+    }
+}
+
+fn list(config: &Config) {
+    let default = config.default_profile();
+    for name in config.profile_names() {
+        let marker = if name == default { "*" } else { " " };
+        let profile = config.get_profile(name).unwrap();
+        println!("{} {:<20} {}", marker, name, profile.api_server());
+    }
+}
+
+fn show(config: &Config, name: &str) {
+    let profile: &Profile = config
+        .get_profile(name)
+        .unwrap_or_else(|| quit_msg!("Profile '{}' not found", name));
+    println!("Profile:    {}", name);
+    match profile.api_key_source() {
+        Some(source) => println!("API key:    ({})", source),
+        None => println!("API key:    {}", mask_api_key(profile.api_key())),
+    }
+    println!("API server: {}", profile.api_server());
+    println!("Git server: {}", profile.git_server());
+
+    // These take precedence over the profile when running other commands
+    let overrides: Vec<&str> = [
+        config::API_KEY_ENV,
+        config::API_SERVER_ENV,
+        config::GIT_SERVER_ENV,
+    ]
+    .iter()
+    .cloned()
+    .filter(|var| config::env_var(var).is_some())
+    .collect();
+    if !overrides.is_empty() {
+        println!("Overridden by: {}", overrides.join(", "));
+    }
+}
+
+// Masks all but the first and last 4 characters of an API key (or all of a short one)
+pub(crate) fn mask_api_key(api_key: &str) -> String {
+    let len = api_key.chars().count();
+    if len <= 8 {
+        return "*".repeat(len);
+    }
+    api_key
+        .chars()
+        .enumerate()
+        .map(|(i, c)| if i < 4 || i >= len - 4 { c } else { '*' })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_mask_api_key() {
+        assert_eq!(
+            mask_api_key("sim1234567890abcdefghijklmno"),
+            "sim1********************lmno"
+        );
+        assert_eq!(mask_api_key("short"), "*****");
+    }
+}